@@ -0,0 +1,245 @@
+#!/usr/bin/env python3
+"""Builds fonts/cjk-subset.ttf from GNU Unifont's unifont.hex.
+
+Only the CJK characters of the Chinese and Japanese translations are kept, so the
+font stays small enough to embed in the binary. Each Unifont bitmap glyph becomes a
+TrueType outline made of one rectangle per run of pixels. Run it again whenever
+locales/zh.toml or locales/ja.toml gain new characters:
+
+    python3 fonts/subset_unifont.py path/to/unifont.hex
+
+Unifont is dual-licensed; the subset is taken under the SIL Open Font License 1.1,
+see fonts/OFL.txt.
+"""
+
+import struct
+import sys
+from pathlib import Path
+
+ROOT = Path(__file__).resolve().parent.parent
+LOCALES = ["zh.toml", "ja.toml"]
+OUTPUT = ROOT / "fonts" / "cjk-subset.ttf"
+
+# A Unifont pixel in font units; glyphs are 16 pixels high with the baseline two pixels
+# above the bottom.
+PIXEL = 64
+EM = 16 * PIXEL
+ASCENT = 14 * PIXEL
+DESCENT = -2 * PIXEL
+FAMILY = "Gobang CJK"
+
+
+def wanted():
+    """The CJK symbols, kana, ideographs and full-width forms of the translations."""
+    chars = set()
+    for name in LOCALES:
+        text = (ROOT / "locales" / name).read_text(encoding="utf-8")
+        chars.update(c for c in text if ord(c) >= 0x2E80)
+    return sorted(chars)
+
+
+def read_hex(path, chars):
+    glyphs = {}
+    codes = {ord(c) for c in chars}
+    for line in Path(path).read_text().splitlines():
+        code, bits = line.split(":")
+        code = int(code, 16)
+        if code in codes:
+            width = len(bits) // 4
+            rows = [int(bits[i : i + width // 4], 16) for i in range(0, len(bits), width // 4)]
+            glyphs[code] = (width, rows)
+    missing = codes - glyphs.keys()
+    if missing:
+        sys.exit("missing from Unifont: " + "".join(map(chr, sorted(missing))))
+    return glyphs
+
+
+def rectangles(width, rows):
+    """Runs of set pixels, merged downwards while the next row has the same run."""
+    open_runs = {}
+    done = []
+    for r, row in enumerate(rows):
+        runs = set()
+        x = 0
+        while x < width:
+            if row >> (width - 1 - x) & 1:
+                start = x
+                while x < width and row >> (width - 1 - x) & 1:
+                    x += 1
+                runs.add((start, x))
+            else:
+                x += 1
+        for run in list(open_runs):
+            if run not in runs:
+                done.append((run, open_runs.pop(run), r))
+        for run in runs:
+            open_runs.setdefault(run, r)
+    done.extend((run, top, len(rows)) for run, top in open_runs.items())
+    return done
+
+
+def outline(width, rows):
+    """The contours of a glyph as clockwise rectangles in font units."""
+    contours = []
+    for (x0, x1), top, bottom in sorted(rectangles(width, rows), key=lambda r: (r[1], r[0])):
+        left, right = x0 * PIXEL, x1 * PIXEL
+        upper, lower = ASCENT - top * PIXEL, ASCENT - bottom * PIXEL
+        contours.append([(left, lower), (left, upper), (right, upper), (right, lower)])
+    return contours
+
+
+def glyph_data(contours):
+    if not contours:
+        return b""
+    points = [p for c in contours for p in c]
+    xs = [x for x, _ in points]
+    ys = [y for _, y in points]
+    data = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)
+    # Every point on the curve, coordinates as 16-bit deltas.
+    data += bytes([0x01]) * len(points)
+    previous = 0
+    for x in xs:
+        data += struct.pack(">h", x - previous)
+        previous = x
+    previous = 0
+    for y in ys:
+        data += struct.pack(">h", y - previous)
+        previous = y
+    return data + b"\0" * (-len(data) % 4)
+
+
+def cmap(codes):
+    """A format 4 subtable mapping each run of consecutive code points to glyphs 1.."""
+    segments = []
+    for gid, code in enumerate(codes, 1):
+        if segments and segments[-1][1] == code - 1 and segments[-1][2] + (code - segments[-1][0]) == gid:
+            segments[-1][1] = code
+        else:
+            segments.append([code, code, gid])
+    segments.append([0xFFFF, 0xFFFF, 0])
+    count = len(segments)
+    power = 1 << (count.bit_length() - 1)
+    body = struct.pack(">HHHH", count * 2, power * 2, power.bit_length() - 1, count * 2 - power * 2)
+    body += b"".join(struct.pack(">H", end) for _, end, _ in segments)
+    body += struct.pack(">H", 0)
+    body += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    body += b"".join(struct.pack(">H", (gid - start) % 0x10000) for start, _, gid in segments)
+    body += struct.pack(">H", 0) * count
+    subtable = struct.pack(">HHH", 4, 6 + len(body), 0) + body
+    return struct.pack(">HHHHL", 0, 1, 3, 1, 12) + subtable
+
+
+def name_table():
+    names = {
+        0: "Glyphs from GNU Unifont by Roman Czyborra, Paul Hardy and contributors",
+        1: FAMILY,
+        2: "Regular",
+        4: FAMILY,
+        5: "Version 1.0",
+        6: FAMILY.replace(" ", "") + "-Regular",
+        13: "This Font Software is licensed under the SIL Open Font License, Version 1.1.",
+        14: "https://openfontlicense.org",
+    }
+    records = b""
+    strings = b""
+    for name_id, text in names.items():
+        encoded = text.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    return struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}L", data)) & 0xFFFFFFFF
+
+
+def build(glyphs):
+    codes = sorted(glyphs)
+    outlines = [[]] + [outline(*glyphs[code]) for code in codes]
+    advances = [EM // 2] + [glyphs[code][0] * PIXEL for code in codes]
+    data = [glyph_data(c) for c in outlines]
+    points = [p for c in outlines for r in c for p in r] or [(0, 0)]
+    x_min = min(x for x, _ in points)
+    y_min = min(y for _, y in points)
+    x_max = max(x for x, _ in points)
+    y_max = max(y for _, y in points)
+    lsbs = [min((x for r in c for x, _ in r), default=0) for c in outlines]
+
+    loca = b""
+    offset = 0
+    for d in data:
+        loca += struct.pack(">L", offset)
+        offset += len(d)
+    loca += struct.pack(">L", offset)
+
+    tables = {
+        b"cmap": cmap(codes),
+        b"glyf": b"".join(data),
+        b"head": struct.pack(
+            ">LLLLHHqqhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0x000B, EM, 0, 0,
+            x_min, y_min, x_max, y_max, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">LhhhHhhhhhh4hhH",
+            0x00010000, ASCENT, DESCENT, 0, max(advances), min(lsbs), 0, x_max,
+            1, 0, 0, 0, 0, 0, 0, 0, len(outlines),
+        ),
+        b"hmtx": b"".join(struct.pack(">Hh", a, l) for a, l in zip(advances, lsbs)),
+        b"loca": loca,
+        b"maxp": struct.pack(
+            ">LHHHHHHHHHHHHHH",
+            0x00010000, len(outlines),
+            max(sum(len(r) for r in c) for c in outlines), max(len(c) for c in outlines),
+            0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+        ),
+        b"name": name_table(),
+        b"OS/2": struct.pack(
+            ">HhHHHhhhhhhhhhhh10B4L4sHHHhhhHHLLhhHHH",
+            4, EM, 400, 5, 0,
+            EM // 2, EM // 2, 0, EM // 4, EM // 2, EM // 2, 0, EM // 2, PIXEL, EM // 4, 0,
+            *([0] * 10), 0, 0, 0, 0, b"NONE", 0x40,
+            min(codes), min(max(codes), 0xFFFF),
+            ASCENT, DESCENT, 0, ASCENT, -DESCENT, 1 << 18, 0,
+            EM // 2, EM, 0, 0x20, 0,
+        ),
+        b"post": struct.pack(">LLhhLLLLL", 0x00030000, 0, DESCENT, PIXEL, 0, 0, 0, 0, 0),
+    }
+
+    count = len(tables)
+    power = 1 << (count.bit_length() - 1)
+    font = struct.pack(">LHHHH", 0x00010000, count, power * 16, power.bit_length() - 1, count * 16 - power * 16)
+    offset = len(font) + 16 * count
+    directory = b""
+    body = b""
+    head_offset = 0
+    for tag in sorted(tables):
+        table = tables[tag]
+        if tag == b"head":
+            head_offset = offset
+        directory += struct.pack(">4sLLL", tag, checksum(table), offset, len(table))
+        padded = table + b"\0" * (-len(table) % 4)
+        body += padded
+        offset += len(padded)
+    font = bytearray(font + directory + body)
+    adjustment = (0xB1B0AFBA - checksum(bytes(font))) & 0xFFFFFFFF
+    font[head_offset + 8 : head_offset + 12] = struct.pack(">L", adjustment)
+    return bytes(font)
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit(__doc__)
+    glyphs = read_hex(sys.argv[1], wanted())
+    OUTPUT.write_bytes(build(glyphs))
+    print(f"{len(glyphs)} glyphs written to {OUTPUT.relative_to(ROOT)}")
+
+
+if __name__ == "__main__":
+    main()
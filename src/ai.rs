@@ -1,23 +1,94 @@
 use log::info;
 use rand::Rng;
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use web_time::{Duration, Instant};
 
-use crate::gui::BOARD_SIZE;
+use crate::{gui::BOARD_SIZE, notation, weights::Weights};
 
 const DEPTH: usize = 2;
 const COLUMN: usize = 15;
 const ROW: usize = 15;
 
+/// Score of a won position, reduced by one for every ply it takes to get there.
+pub const WIN_SCORE: i32 = 1_000_000_000;
+const MAX_PLY: i32 = (ROW * COLUMN) as i32;
+
 type TypeScoreAllArr = Vec<(i32, Vec<(usize, usize)>, (i32, i32))>;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameState {
     Idle,
     Human,
     AI,
+    Draw,
+}
+
+/// Five stones in a row: the first of them and the step from one to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WinLine {
+    pub start: (usize, usize),
+    pub direction: (isize, isize),
+}
+
+impl WinLine {
+    /// The five cells from `start`.
+    pub fn cells(&self) -> [(usize, usize); 5] {
+        let (x, y) = self.start;
+        let (dx, dy) = self.direction;
+        std::array::from_fn(|k| {
+            let k = k as isize;
+            (
+                (x as isize + dx * k) as usize,
+                (y as isize + dy * k) as usize,
+            )
+        })
+    }
+}
+
+/// A forced result found by the search, counted in the searching side's own moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outlook {
+    WinIn(usize),
+    LossIn(usize),
+}
+
+impl Outlook {
+    pub fn from_score(score: i32) -> Option<Outlook> {
+        if score >= WIN_SCORE - MAX_PLY {
+            Some(Outlook::WinIn(((WIN_SCORE - score) as usize).div_ceil(2)))
+        } else if score <= -(WIN_SCORE - MAX_PLY) {
+            Some(Outlook::LossIn(((WIN_SCORE + score) as usize).div_ceil(2)))
+        } else {
+            None
+        }
+    }
+}
+
+/// A suggested move for the human together with the reply the AI expects to it.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub step: (usize, usize),
+    pub score: i32,
+    pub reply: Option<(usize, usize)>,
+    /// The next best moves, best first.
+    pub alternatives: Vec<(usize, usize)>,
+}
+
+/// A root move scored by `AI::analyse`, with the line the search expects after it.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub step: (usize, usize),
+    pub score: i32,
+    pub pv: Vec<(usize, usize)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct GobangZobrist {
     black: [[u32; BOARD_SIZE]; BOARD_SIZE],
     white: [[u32; BOARD_SIZE]; BOARD_SIZE],
@@ -55,7 +126,7 @@ impl GobangZobrist {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AI {
     ai_steps: Vec<(usize, usize)>,
     ai_steps_st: HashSet<(usize, usize)>,
@@ -65,6 +136,8 @@ pub struct AI {
     all_steps_st: HashSet<(usize, usize)>,
     full_steps: HashSet<(usize, usize)>,
     next_step: (usize, usize),
+    /// Root score of the last search, from the AI's point of view.
+    pub last_score: i32,
 
     cut_cnt: usize,
     search_cnt: usize,
@@ -72,31 +145,29 @@ pub struct AI {
 
     pub state: GameState,
     pub depth: usize,
+    /// When set, `ai` deepens iteratively up to `depth` until the budget runs out.
+    pub time_limit: Option<Duration>,
+    root_depth: usize,
+    deadline: Option<Instant>,
+    /// Aborts the running search as soon as it is set.
+    pub cancel: Option<Arc<AtomicBool>>,
+    timeout: bool,
+    /// Principal variation found below each ply of the current search.
+    pv: Vec<Vec<(usize, usize)>>,
+    /// Root moves skipped by the search, used to find the next best line.
+    excluded: HashSet<(usize, usize)>,
 
     pub ai_black: bool,
+    /// Also declare a draw once neither side can complete five anywhere.
+    pub early_draw: bool,
+    /// Standard gomoku: only exactly five in a row wins, not an overline of six or more.
+    pub exact_five: bool,
     zobrist: GobangZobrist,
     evaluation_cache_black: HashMap<u32, i32>,
     evaluation_cache_white: HashMap<u32, i32>,
+    weights: Weights,
 }
 
-const SHAPE_SCORE: &[(i32, &[usize])] = &[
-    (50, &[0, 1, 1, 0, 0]),
-    (50, &[0, 0, 1, 1, 0]),
-    (200, &[1, 1, 0, 1, 0]),
-    (500, &[0, 0, 1, 1, 1]),
-    (500, &[1, 1, 1, 0, 0]),
-    (5000, &[0, 1, 1, 1, 0]),
-    (5000, &[0, 1, 0, 1, 1, 0]),
-    (5000, &[0, 1, 1, 0, 1, 0]),
-    (5000, &[1, 1, 1, 0, 1]),
-    (5000, &[1, 1, 0, 1, 1]),
-    (5000, &[1, 0, 1, 1, 1]),
-    (5000, &[1, 1, 1, 1, 0]),
-    (5000, &[0, 1, 1, 1, 1]),
-    (50000, &[0, 1, 1, 1, 1, 0]),
-    (99999999, &[1, 1, 1, 1, 1]),
-];
-
 impl AI {
     pub fn new() -> AI {
         let full_steps: HashSet<(usize, usize)> = (0..ROW)
@@ -111,29 +182,230 @@ impl AI {
             all_steps_st: HashSet::new(),
             full_steps,
             next_step: (0, 0),
+            last_score: 0,
             search_cnt: 0,
             cut_cnt: 0,
             cache_hit: 0,
             state: GameState::Idle,
             depth: DEPTH,
+            time_limit: None,
+            root_depth: DEPTH,
+            deadline: None,
+            cancel: None,
+            timeout: false,
+            pv: Vec::new(),
+            excluded: HashSet::new(),
             zobrist: GobangZobrist::new(),
             ai_black: false,
+            early_draw: true,
+            exact_five: false,
             evaluation_cache_black: HashMap::new(),
             evaluation_cache_white: HashMap::new(),
+            weights: Weights::default(),
         }
     }
 
-    pub fn ai(&mut self) -> (usize, usize) {
+    pub fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+        self.evaluation_cache_black.clear();
+        self.evaluation_cache_white.clear();
+    }
+
+    /// Searches and plays the AI's move, or returns `None` if the board is full.
+    pub fn ai(&mut self) -> Option<(usize, usize)> {
         self.cut_cnt = 0;
         self.search_cnt = 0;
         self.cache_hit = 0;
-        self.negamax(true, self.depth, i32::MIN >> 1, i32::MAX >> 1);
+        // Played if the search has nothing to look at, e.g. on an empty board.
+        self.next_step = self.fallback_move()?;
+        if let Some(time_limit) = self.time_limit {
+            let start = Instant::now();
+            let mut best = None;
+            for depth in 1..=self.depth {
+                // The first iteration always completes so that there is a move to play.
+                self.deadline = (depth > 1).then(|| start + time_limit);
+                self.start_search(depth);
+                let score = self.negamax(true, depth, i32::MIN >> 1, i32::MAX >> 1);
+                if self.timeout {
+                    break;
+                }
+                best = Some((self.next_step, score));
+                if Outlook::from_score(score).is_some() {
+                    break;
+                }
+            }
+            self.deadline = None;
+            if let Some((step, score)) = best {
+                self.next_step = step;
+                self.last_score = score;
+            }
+        } else {
+            self.start_search(self.depth);
+            self.last_score = self.negamax(true, self.depth, i32::MIN >> 1, i32::MAX >> 1);
+        }
+        if self.drawn() {
+            self.last_score = 0;
+        }
         info!(
-            "search count: {}; cut count: {}; cache hit: {}",
-            self.search_cnt, self.cut_cnt, self.cache_hit
+            "move: {}; score: {}; search count: {}; cut count: {}; cache hit: {}",
+            notation::to_notation(self.next_step),
+            self.last_score,
+            self.search_cnt,
+            self.cut_cnt,
+            self.cache_hit
         );
         self.ai_step(self.next_step.0, self.next_step.1);
-        self.next_step
+        Some(self.next_step)
+    }
+
+    /// The centre, else an empty cell next to a stone, else any empty cell.
+    fn fallback_move(&self) -> Option<(usize, usize)> {
+        let centre = (ROW / 2, COLUMN / 2);
+        if !self.all_steps_st.contains(&centre) {
+            return Some(centre);
+        }
+        let mut empty = (0..ROW)
+            .flat_map(|i| (0..COLUMN).map(move |j| (i, j)))
+            .filter(|step| !self.all_steps_st.contains(step));
+        let first = empty.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(empty)
+                .find(|&(x, y)| self.has_neighbor(x, y))
+                .unwrap_or(first),
+        )
+    }
+
+    fn start_search(&mut self, depth: usize) {
+        self.timeout = false;
+        self.root_depth = depth;
+        self.pv = vec![Vec::new(); depth + 1];
+    }
+
+    /// Scores every candidate move of the AI's side with a full-window search of `depth`
+    /// plies, best first. Returns an empty list if the search is cancelled.
+    pub fn analyse(&mut self, depth: usize) -> Vec<Candidate> {
+        self.start_search(depth.max(1));
+        if self.winning_line().is_some() {
+            return Vec::new();
+        }
+        let mut blank_steps: Vec<(usize, usize)> = self
+            .full_steps
+            .difference(&self.all_steps_st)
+            .copied()
+            .collect();
+        self.order(&mut blank_steps);
+        let mut candidates = Vec::new();
+        for (tx, ty) in blank_steps {
+            if !self.has_neighbor(tx, ty) {
+                continue;
+            }
+            self.ai_step(tx, ty);
+            let score = -self.negamax(false, self.root_depth - 1, i32::MIN >> 1, i32::MAX >> 1);
+            self.undo_step(true, tx, ty);
+            if self.timeout {
+                return Vec::new();
+            }
+            let mut pv = vec![(tx, ty)];
+            pv.extend(self.pv[1].iter().copied());
+            candidates.push(Candidate {
+                step: (tx, ty),
+                score,
+                pv,
+            });
+        }
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+        candidates
+    }
+
+    /// Scores a single move of the AI's side with a full-window search of `depth` plies.
+    pub fn score_move(&mut self, depth: usize, (x, y): (usize, usize)) -> i32 {
+        self.start_search(depth.max(1));
+        self.ai_step(x, y);
+        let score = -self.negamax(false, self.root_depth - 1, i32::MIN >> 1, i32::MAX >> 1);
+        self.undo_step(true, x, y);
+        score
+    }
+
+    /// Finds the best `k` distinct moves for the AI, best first, by searching again with
+    /// the moves already found excluded at the root. Honours `time_limit` like `ai`,
+    /// returning the lines of the deepest iteration that completed.
+    pub fn multi_pv(&mut self, k: usize) -> Vec<Candidate> {
+        let start = Instant::now();
+        let mut lines = Vec::new();
+        'deepening: for depth in 1..=self.depth {
+            self.deadline = self
+                .time_limit
+                .filter(|_| depth > 1)
+                .map(|time_limit| start + time_limit);
+            self.excluded.clear();
+            let mut current = Vec::new();
+            while current.len() < k {
+                self.start_search(depth);
+                let score = self.negamax(true, depth, i32::MIN >> 1, i32::MAX >> 1);
+                if self.timeout {
+                    break 'deepening;
+                }
+                if score == i32::MIN >> 1 {
+                    // Every remaining root move is excluded.
+                    break;
+                }
+                current.push(Candidate {
+                    step: self.next_step,
+                    score,
+                    pv: self.pv[0].clone(),
+                });
+                self.excluded.insert(self.next_step);
+            }
+            lines = current;
+        }
+        self.deadline = None;
+        self.excluded.clear();
+        if self.drawn() {
+            for line in &mut lines {
+                line.score = 0;
+            }
+        }
+        lines
+    }
+
+    /// A copy of this engine with the roles swapped, so that searching it plays the
+    /// human's side without touching the game's own state.
+    pub fn for_human(&self) -> AI {
+        let mut ai = self.clone();
+        std::mem::swap(&mut ai.ai_steps, &mut ai.human_steps);
+        std::mem::swap(&mut ai.ai_steps_st, &mut ai.human_steps_st);
+        ai.ai_black = !self.ai_black;
+        ai.state = GameState::Idle;
+        ai
+    }
+
+    /// Searches the `k` best moves for the human and the AI's expected reply to the best.
+    pub fn hint(&self, k: usize) -> Hint {
+        let mut helper = self.for_human();
+        let lines = helper.multi_pv(k.max(1));
+        let Some(best) = lines.first() else {
+            // Nothing to search around on an empty board.
+            return Hint {
+                step: (ROW / 2, COLUMN / 2),
+                score: 0,
+                reply: None,
+                alternatives: Vec::new(),
+            };
+        };
+        let (step, score) = (best.step, best.score);
+        let reply = best.pv.get(1).copied().or_else(|| {
+            helper.ai_step(step.0, step.1);
+            (!helper.is_game_over())
+                .then(|| helper.for_human().ai())
+                .flatten()
+        });
+        Hint {
+            step,
+            score,
+            reply,
+            alternatives: lines[1..].iter().map(|line| line.step).collect(),
+        }
     }
 
     pub fn human_step(&mut self, x: usize, y: usize) {
@@ -152,20 +424,80 @@ impl AI {
         self.zobrist.update(x, y, self.ai_black);
     }
 
+    fn undo_step(&mut self, is_ai: bool, x: usize, y: usize) {
+        if is_ai {
+            self.ai_steps.pop();
+            self.ai_steps_st.remove(&(x, y));
+            self.zobrist.update(x, y, self.ai_black);
+        } else {
+            self.human_steps.pop();
+            self.human_steps_st.remove(&(x, y));
+            self.zobrist.update(x, y, !self.ai_black);
+        }
+        self.all_steps.pop();
+        self.all_steps_st.remove(&(x, y));
+    }
+
+    /// Static evaluation of the current position for the side given by `is_ai`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn evaluate(&mut self, is_ai: bool) -> i32 {
+        let blank_steps: Vec<(usize, usize)> = self
+            .full_steps
+            .difference(&self.all_steps_st)
+            .copied()
+            .collect();
+        self.evalution(is_ai, HashSet::from_iter(blank_steps.iter()))
+    }
+
     fn negamax(&mut self, is_ai: bool, depth: usize, mut alpha: i32, beta: i32) -> i32 {
         let mut blank_steps: Vec<(usize, usize)> = self
             .full_steps
             .difference(&self.all_steps_st)
             .copied()
             .collect();
-        if AI::game_win(&self.ai_steps_st) || AI::game_win(&self.human_steps_st) || depth == 0 {
+        let (my_steps_st, enemy_steps_st) = if is_ai {
+            (&self.ai_steps_st, &self.human_steps_st)
+        } else {
+            (&self.human_steps_st, &self.ai_steps_st)
+        };
+        let ply = (self.root_depth - depth) as i32;
+        self.pv[ply as usize].clear();
+        // The root is searched even if it is decided, so that there is a move to play.
+        // Early draws are left to `ai` and `is_game_over`, as finding them scans the board.
+        if ply > 0 {
+            if AI::game_win(enemy_steps_st, self.exact_five).is_some() {
+                return -(WIN_SCORE - ply);
+            }
+            if AI::game_win(my_steps_st, self.exact_five).is_some() {
+                return WIN_SCORE - ply;
+            }
+            if blank_steps.is_empty() {
+                return 0;
+            }
+        }
+        if depth == 0 {
             return self.evalution(is_ai, HashSet::from_iter(blank_steps.iter()));
         }
+        if let Some(deadline) = self.deadline
+            && Instant::now() >= deadline
+        {
+            self.timeout = true;
+        }
+        if let Some(cancel) = &self.cancel
+            && cancel.load(Ordering::Relaxed)
+        {
+            self.timeout = true;
+        }
+        if self.timeout {
+            return 0;
+        }
         self.order(&mut blank_steps);
         for (tx, ty) in blank_steps {
             self.search_cnt += 1;
 
-            if !self.has_neighbor(tx, ty) {
+            if !self.has_neighbor(tx, ty)
+                || (depth == self.root_depth && self.excluded.contains(&(tx, ty)))
+            {
                 continue;
             }
             if is_ai {
@@ -174,22 +506,18 @@ impl AI {
                 self.human_step(tx, ty);
             }
             let value = -self.negamax(!is_ai, depth - 1, -beta, -alpha);
-            if is_ai {
-                self.ai_steps.pop();
-                self.ai_steps_st.remove(&(tx, ty));
-                self.zobrist.update(tx, ty, self.ai_black);
-            } else {
-                self.human_steps.pop();
-                self.human_steps_st.remove(&(tx, ty));
-                self.zobrist.update(tx, ty, !self.ai_black);
+            self.undo_step(is_ai, tx, ty);
+            if self.timeout {
+                return alpha;
             }
-            self.all_steps.pop();
-            self.all_steps_st.remove(&(tx, ty));
             if value > alpha {
                 // println!("{};alpha:{};beta:{}", value, alpha, beta);
-                if depth == self.depth {
+                if depth == self.root_depth {
                     self.next_step = (tx, ty);
                 }
+                let mut pv = vec![(tx, ty)];
+                pv.extend(self.pv[ply as usize + 1].iter().copied());
+                self.pv[ply as usize] = pv;
                 if value >= beta {
                     self.cut_cnt += 1;
                     return beta;
@@ -296,7 +624,8 @@ impl AI {
                 &mut enemy_score_all_arr,
             );
         }
-        let ret = (my_score as f32 - enemy_score as f32 * 0.1) as i32;
+        let ret = (my_score as f32 * self.weights.attack
+            - enemy_score as f32 * self.weights.defence) as i32;
         if (is_ai && self.ai_black) || (!is_ai && !self.ai_black) {
             self.evaluation_cache_black
                 .insert(self.zobrist.get_hash(), ret);
@@ -349,12 +678,12 @@ impl AI {
             }
             let tmp_shape5 = vec![pos[0], pos[1], pos[2], pos[3], pos[4]];
             let tmp_shape6 = vec![pos[0], pos[1], pos[2], pos[3], pos[4], pos[5]];
-            for (score, shape) in SHAPE_SCORE {
-                if (tmp_shape5 == shape.to_vec() || tmp_shape6 == shape.to_vec())
-                    && *score > max_score_shape.0
+            for shape in &self.weights.shapes {
+                if (tmp_shape5 == shape.pattern || tmp_shape6 == shape.pattern)
+                    && shape.score > max_score_shape.0
                 {
                     max_score_shape = (
-                        *score,
+                        shape.score,
                         vec![
                             ((x + offset * dx) as usize, (y + offset * dy) as usize),
                             (
@@ -381,11 +710,14 @@ impl AI {
         }
 
         if !max_score_shape.1.is_empty() {
+            let threshold = self.weights.combination_threshold;
             for item in score_all_arr.iter() {
                 for pt1 in item.1.clone() {
                     for pt2 in max_score_shape.1.clone() {
-                        if pt1 == pt2 && max_score_shape.0 > 10 && item.0 > 10 {
-                            add_score += item.0 + max_score_shape.0;
+                        if pt1 == pt2 && max_score_shape.0 > threshold && item.0 > threshold {
+                            add_score += ((item.0 + max_score_shape.0) as f32
+                                * self.weights.combination_bonus)
+                                as i32;
                         }
                     }
                 }
@@ -437,50 +769,178 @@ impl AI {
     }
 
     pub fn is_game_over(&mut self) -> bool {
-        if AI::game_win(&self.ai_steps_st) {
+        if AI::game_win(&self.ai_steps_st, self.exact_five).is_some() {
             self.state = GameState::AI;
             return true;
-        } else if AI::game_win(&self.human_steps_st) {
+        } else if AI::game_win(&self.human_steps_st, self.exact_five).is_some() {
             self.state = GameState::Human;
             return true;
+        } else if self.all_steps.len() == ROW * COLUMN || self.drawn() {
+            self.state = GameState::Draw;
+            return true;
         }
         false
     }
 
-    fn game_win(steps_st: &HashSet<(usize, usize)>) -> bool {
-        for i in 0..ROW {
-            for j in 0..COLUMN {
-                if (j < COLUMN - 4
-                    && steps_st.contains(&(i, j))
-                    && steps_st.contains(&(i, j + 1))
-                    && steps_st.contains(&(i, j + 2))
-                    && steps_st.contains(&(i, j + 3))
-                    && steps_st.contains(&(i, j + 4)))
-                    || (i < ROW - 4
-                        && steps_st.contains(&(i, j))
-                        && steps_st.contains(&(i + 1, j))
-                        && steps_st.contains(&(i + 2, j))
-                        && steps_st.contains(&(i + 3, j))
-                        && steps_st.contains(&(i + 4, j)))
-                    || (i < ROW - 4
-                        && j < COLUMN - 4
-                        && steps_st.contains(&(i, j))
-                        && steps_st.contains(&(i + 1, j + 1))
-                        && steps_st.contains(&(i + 2, j + 2))
-                        && steps_st.contains(&(i + 3, j + 3))
-                        && steps_st.contains(&(i + 4, j + 4)))
-                    || (i < ROW - 4
-                        && j > 3
-                        && steps_st.contains(&(i, j))
-                        && steps_st.contains(&(i + 1, j - 1))
-                        && steps_st.contains(&(i + 2, j - 2))
-                        && steps_st.contains(&(i + 3, j - 3))
-                        && steps_st.contains(&(i + 4, j - 4)))
-                {
-                    return true;
+    /// With early draws, whether neither side can complete five anywhere.
+    fn drawn(&self) -> bool {
+        self.early_draw
+            && !self.five_possible(&self.human_steps_st, &self.ai_steps_st)
+            && !self.five_possible(&self.ai_steps_st, &self.human_steps_st)
+    }
+
+    /// Whether the side owning `own_steps_st` can still complete five, i.e. some window
+    /// of five cells holds none of `enemy_steps_st`. With `exact_five` the window must
+    /// not be flanked by its own stones, which would make an overline.
+    fn five_possible(
+        &self,
+        own_steps_st: &HashSet<(usize, usize)>,
+        enemy_steps_st: &HashSet<(usize, usize)>,
+    ) -> bool {
+        let contains = |steps_st: &HashSet<(usize, usize)>, x: i32, y: i32| {
+            x >= 0 && y >= 0 && steps_st.contains(&(x as usize, y as usize))
+        };
+        for i in 0..ROW as i32 {
+            for j in 0..COLUMN as i32 {
+                for (dx, dy) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                    let (ex, ey) = (i + 4 * dx, j + 4 * dy);
+                    if ex >= ROW as i32 || ey < 0 || ey >= COLUMN as i32 {
+                        continue;
+                    }
+                    if (0..5).all(|k| !contains(enemy_steps_st, i + k * dx, j + k * dy))
+                        && !(self.exact_five
+                            && (contains(own_steps_st, i - dx, j - dy)
+                                || contains(own_steps_st, i + 5 * dx, j + 5 * dy)))
+                    {
+                        return true;
+                    }
                 }
             }
         }
         false
     }
+
+    /// The five in a row of whichever side has made one.
+    pub fn winning_line(&self) -> Option<WinLine> {
+        AI::game_win(&self.ai_steps_st, self.exact_five)
+            .or_else(|| AI::game_win(&self.human_steps_st, self.exact_five))
+    }
+
+    /// The first five in a row among `steps_st`, scanning from the top-left corner. With
+    /// `exact_five` a line of six or more does not count.
+    fn game_win(steps_st: &HashSet<(usize, usize)>, exact_five: bool) -> Option<WinLine> {
+        let stone =
+            |x: isize, y: isize| x >= 0 && y >= 0 && steps_st.contains(&(x as usize, y as usize));
+        for i in 0..ROW as isize {
+            for j in 0..COLUMN as isize {
+                for (dx, dy) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                    if (0..5).all(|k| stone(i + k * dx, j + k * dy))
+                        && !(exact_five && (stone(i - dx, j - dy) || stone(i + 5 * dx, j + 5 * dy)))
+                    {
+                        return Some(WinLine {
+                            start: (i as usize, j as usize),
+                            direction: (dx, dy),
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlines_win_only_in_freestyle() {
+        let six: HashSet<_> = (2..8).map(|y| (7, y)).collect();
+        assert!(AI::game_win(&six, false).is_some());
+        assert_eq!(AI::game_win(&six, true), None);
+
+        let mut five: HashSet<_> = (2..7).map(|y| (7, y)).collect();
+        five.insert((7, 8));
+        let line = AI::game_win(&five, true).unwrap();
+        assert_eq!(line.cells(), [(7, 2), (7, 3), (7, 4), (7, 5), (7, 6)]);
+    }
+
+    /// An engine with the AI's stones where `ai` holds and the human's elsewhere,
+    /// leaving `empty` free.
+    fn position(ai: impl Fn(usize, usize) -> bool, empty: &[(usize, usize)]) -> AI {
+        let mut engine = AI::new();
+        engine.depth = 2;
+        for x in 0..ROW {
+            for y in 0..COLUMN {
+                if empty.contains(&(x, y)) {
+                    continue;
+                }
+                if ai(x, y) {
+                    engine.ai_step(x, y);
+                } else {
+                    engine.human_step(x, y);
+                }
+            }
+        }
+        engine
+    }
+
+    /// Every window of five holds at least two stones of each side.
+    fn dead(x: usize, y: usize) -> bool {
+        (x + 2 * y) % 4 < 2
+    }
+
+    #[test]
+    fn moves_on_an_empty_cell_once_the_game_is_drawn() {
+        let empty = [(0, 0), (7, 7), (14, 14)];
+        let mut engine = position(dead, &empty);
+        // What the previous move left behind.
+        engine.next_step = (7, 6);
+        let step = engine.ai().unwrap();
+        assert!(empty.contains(&step));
+        assert_eq!(engine.last_score, 0);
+        assert!(engine.is_game_over());
+        assert_eq!(engine.state, GameState::Draw);
+    }
+
+    #[test]
+    fn moves_on_an_empty_cell_once_the_game_is_won() {
+        let mut engine = AI::new();
+        engine.depth = 2;
+        for y in 3..8 {
+            engine.ai_step(7, y);
+        }
+        for y in 3..7 {
+            engine.human_step(8, y);
+        }
+        engine.next_step = (7, 7);
+        let step = engine.ai().unwrap();
+        assert_eq!(engine.all_steps_st.len(), 10);
+        assert!(!engine.ai_steps[..5].contains(&step));
+        assert!(!engine.human_steps.contains(&step));
+    }
+
+    #[test]
+    fn no_move_on_a_full_board() {
+        let mut engine = position(dead, &[]);
+        assert_eq!(engine.ai(), None);
+        assert_eq!(engine.all_steps.len(), ROW * COLUMN);
+    }
+
+    #[test]
+    fn overline_windows_are_dead_with_exact_five() {
+        // Filling the one gap in a row of six makes an overline, not five.
+        let mut engine = position(|x, y| x == 7 && y < 6, &[(7, 3)]);
+        assert!(engine.five_possible(&engine.ai_steps_st, &engine.human_steps_st));
+        engine.exact_five = true;
+        assert!(!engine.five_possible(&engine.ai_steps_st, &engine.human_steps_st));
+    }
+
+    #[test]
+    fn finds_fives_along_the_edges() {
+        let anti_diagonal: HashSet<_> = (0..5).map(|k| (k, 14 - k)).collect();
+        let line = AI::game_win(&anti_diagonal, true).unwrap();
+        assert_eq!(line.start, (0, 14));
+        assert_eq!(line.direction, (1, -1));
+    }
 }
@@ -0,0 +1,214 @@
+//! Game clocks: sudden death, Fischer increment and byo-yomi time controls.
+
+use serde::{Deserialize, Serialize};
+use web_time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TimeControl {
+    /// The main time has to last the whole game.
+    SuddenDeath { main_ms: u64 },
+    /// Every move adds the increment back to the clock.
+    Fischer { main_ms: u64, increment_ms: u64 },
+    /// Once the main time is used up, every move has to be made within a period; a
+    /// period overrun is lost, and the game with the last one.
+    ByoYomi {
+        main_ms: u64,
+        period_ms: u64,
+        periods: u32,
+    },
+}
+
+impl TimeControl {
+    pub const PRESETS: [TimeControl; 3] = [
+        TimeControl::SuddenDeath { main_ms: 300_000 },
+        TimeControl::Fischer {
+            main_ms: 180_000,
+            increment_ms: 2_000,
+        },
+        TimeControl::ByoYomi {
+            main_ms: 300_000,
+            period_ms: 30_000,
+            periods: 3,
+        },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeControl::SuddenDeath { .. } => "Sudden death",
+            TimeControl::Fischer { .. } => "Fischer",
+            TimeControl::ByoYomi { .. } => "Byo-yomi",
+        }
+    }
+
+    fn main_ms(&self) -> u64 {
+        match *self {
+            TimeControl::SuddenDeath { main_ms }
+            | TimeControl::Fischer { main_ms, .. }
+            | TimeControl::ByoYomi { main_ms, .. } => main_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    pub control: TimeControl,
+    /// Main time left in milliseconds.
+    pub remaining_ms: u64,
+    /// Byo-yomi periods left.
+    #[serde(default)]
+    pub periods: u32,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            remaining_ms: control.main_ms(),
+            periods: match control {
+                TimeControl::ByoYomi { periods, .. } => periods,
+                _ => 0,
+            },
+        }
+    }
+
+    /// Main time, periods and time in the current period left after thinking for
+    /// `elapsed_ms` on the current move, or `None` once the flag has fallen.
+    fn after(&self, elapsed_ms: u64) -> Option<(u64, u32, u64)> {
+        let period_ms = match self.control {
+            TimeControl::ByoYomi { period_ms, .. } => period_ms.max(1),
+            _ => 0,
+        };
+        if elapsed_ms < self.remaining_ms {
+            return Some((self.remaining_ms - elapsed_ms, self.periods, period_ms));
+        }
+        let over = elapsed_ms - self.remaining_ms;
+        let used = over / period_ms.max(1);
+        (period_ms > 0 && used < self.periods as u64)
+            .then(|| (0, self.periods - used as u32, period_ms - over % period_ms))
+    }
+
+    pub fn flagged(&self, elapsed_ms: u64) -> bool {
+        self.after(elapsed_ms).is_none()
+    }
+
+    /// Stops the clock after a move that took `elapsed_ms`, adding the increment.
+    /// Returns `false` if the flag fell before the move was made.
+    pub fn punch(&mut self, elapsed_ms: u64) -> bool {
+        let Some((remaining_ms, periods, _)) = self.after(elapsed_ms) else {
+            self.remaining_ms = 0;
+            self.periods = 0;
+            return false;
+        };
+        self.remaining_ms = remaining_ms;
+        self.periods = periods;
+        if let TimeControl::Fischer { increment_ms, .. } = self.control {
+            self.remaining_ms += increment_ms;
+        }
+        true
+    }
+
+    /// Thinking time for the next move: a share of the main time plus most of what the
+    /// move earns back, never more than half of what is left.
+    pub fn budget(&self) -> Duration {
+        let share = self.remaining_ms / 30;
+        let ms = match self.control {
+            TimeControl::SuddenDeath { .. } => share,
+            TimeControl::Fischer { increment_ms, .. } => {
+                (share + increment_ms * 3 / 4).min(self.remaining_ms / 2)
+            }
+            TimeControl::ByoYomi { period_ms, .. } if self.periods > 0 => share + period_ms * 3 / 4,
+            TimeControl::ByoYomi { .. } => share,
+        };
+        Duration::from_millis(ms.max(1))
+    }
+
+    /// The clock face after `elapsed_ms` on the current move, e.g. `4:59`, `4:59 + 3×30s`
+    /// or, in byo-yomi, `0:25 (3)`.
+    pub fn show(&self, elapsed_ms: u64) -> String {
+        let Some((remaining_ms, periods, period_left_ms)) = self.after(elapsed_ms) else {
+            return format_ms(0);
+        };
+        match self.control {
+            TimeControl::ByoYomi { period_ms, .. } if remaining_ms > 0 => format!(
+                "{} + {}×{}s",
+                format_ms(remaining_ms),
+                periods,
+                period_ms / 1000
+            ),
+            TimeControl::ByoYomi { .. } => format!("{} ({})", format_ms(period_left_ms), periods),
+            _ => format_ms(remaining_ms),
+        }
+    }
+}
+
+/// `m:ss`, with tenths of a second under ten seconds.
+pub fn format_ms(ms: u64) -> String {
+    if ms < 10_000 {
+        format!("0:0{}.{}", ms / 1000, ms % 1000 / 100)
+    } else {
+        format!("{}:{:02}", ms / 60_000, ms % 60_000 / 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYO_YOMI: TimeControl = TimeControl::ByoYomi {
+        main_ms: 1_000,
+        period_ms: 500,
+        periods: 3,
+    };
+
+    #[test]
+    fn main_time_overruns_into_the_first_period() {
+        let mut clock = Clock::new(BYO_YOMI);
+        assert!(clock.punch(1_200));
+        assert_eq!((clock.remaining_ms, clock.periods), (0, 3));
+        assert_eq!(clock.show(100), "0:00.4 (3)");
+        // A period is only lost when a move overruns it.
+        assert!(clock.punch(499));
+        assert_eq!(clock.periods, 3);
+        assert!(clock.punch(500));
+        assert_eq!(clock.periods, 2);
+    }
+
+    #[test]
+    fn one_move_can_use_up_several_periods() {
+        let mut clock = Clock::new(BYO_YOMI);
+        assert_eq!(clock.show(2_100), "0:00.4 (1)");
+        assert!(clock.punch(2_100));
+        assert_eq!((clock.remaining_ms, clock.periods), (0, 1));
+    }
+
+    #[test]
+    fn the_flag_falls_exactly_at_the_end_of_the_last_period() {
+        let clock = Clock::new(BYO_YOMI);
+        assert!(!clock.flagged(2_499));
+        assert!(clock.flagged(2_500));
+
+        let mut clock = Clock::new(BYO_YOMI);
+        assert!(clock.punch(2_000));
+        assert_eq!(clock.periods, 1);
+        assert!(!clock.flagged(499));
+        assert!(!clock.punch(500));
+        assert_eq!((clock.remaining_ms, clock.periods), (0, 0));
+        assert_eq!(clock.show(0), "0:00.0");
+    }
+
+    #[test]
+    fn fischer_adds_the_increment_after_each_move() {
+        let mut clock = Clock::new(TimeControl::Fischer {
+            main_ms: 1_000,
+            increment_ms: 200,
+        });
+        assert!(clock.punch(300));
+        assert_eq!(clock.remaining_ms, 900);
+        assert!(clock.punch(899));
+        assert_eq!(clock.remaining_ms, 201);
+        assert!(clock.flagged(201));
+        assert!(!clock.punch(201));
+        assert_eq!(clock.remaining_ms, 0);
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::mpsc::Sender;
+
+/// Opens a file picker and sends the chosen file's name and contents through `tx`.
+pub fn pick_file(tx: Sender<(String, Vec<u8>)>, extensions: &'static [&'static str]) {
+    let task = async move {
+        if let Some(file) = rfd::AsyncFileDialog::new()
+            .add_filter("file", extensions)
+            .pick_file()
+            .await
+        {
+            let content = file.read().await;
+            tx.send((file.file_name(), content))
+                .expect("Can not send data");
+        }
+    };
+    #[cfg(target_arch = "wasm32")]
+    wasm_bindgen_futures::spawn_local(task);
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::task::spawn(task);
+}
+
+/// Lets the user choose where to write `content` natively, or downloads it on the web.
+pub fn save_file(file_name: String, content: Vec<u8>) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(content.as_slice()));
+        let Ok(blob) = web_sys::Blob::new_with_u8_array_sequence(&parts) else {
+            return;
+        };
+        let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) else {
+            return;
+        };
+        if let Some(anchor) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.create_element("a").ok())
+            .and_then(|a| a.dyn_into::<web_sys::HtmlAnchorElement>().ok())
+        {
+            anchor.set_href(&url);
+            anchor.set_download(&file_name);
+            anchor.click();
+        }
+        web_sys::Url::revoke_object_url(&url).ok();
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio::task::spawn(async move {
+        if let Some(file) = rfd::AsyncFileDialog::new()
+            .set_file_name(&file_name)
+            .save_file()
+            .await
+            && let Err(e) = std::fs::write(file.path(), content)
+        {
+            log::error!("Can not save {}: {}", file_name, e);
+        }
+    });
+}
+
+/// Key-value storage that survives page reloads, backed by `localStorage`.
+#[cfg(target_arch = "wasm32")]
+pub mod storage {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    pub fn set(key: &str, value: &str) {
+        if let Some(storage) = local_storage() {
+            storage.set_item(key, value).ok();
+        }
+    }
+
+    pub fn get(key: &str) -> Option<String> {
+        local_storage()?.get_item(key).ok()?
+    }
+
+    /// All keys starting with `prefix`.
+    pub fn keys(prefix: &str) -> Vec<String> {
+        let Some(storage) = local_storage() else {
+            return Vec::new();
+        };
+        let len = storage.length().unwrap_or(0);
+        (0..len)
+            .filter_map(|i| storage.key(i).ok().flatten())
+            .filter(|key| key.starts_with(prefix))
+            .collect()
+    }
+}
@@ -0,0 +1,81 @@
+//! CJK font for the Chinese and Japanese translations, which egui's default fonts do
+//! not cover. A subset covering the interface is bundled; on desktop a system CJK font,
+//! when there is one, takes precedence for nicer glyphs and for text typed by players.
+//! Both are added as fallbacks behind the default fonts.
+
+use eframe::egui::{
+    Context, FontData, FontFamily,
+    epaint::text::{FontInsert, FontPriority, InsertFontFamily},
+};
+
+/// The CJK characters of the translations, generated from GNU Unifont by
+/// `fonts/subset_unifont.py`, under the SIL Open Font License (`fonts/OFL.txt`).
+const BUNDLED: &[u8] = include_bytes!("../fonts/cjk-subset.ttf");
+
+/// Fonts with Chinese and Japanese glyphs on Windows, macOS and Linux, most complete
+/// first.
+#[cfg(not(target_arch = "wasm32"))]
+const SYSTEM_FONTS: &[&str] = &[
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\YuGothM.ttc",
+    "C:\\Windows\\Fonts\\msgothic.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+];
+
+fn install(ctx: &Context, name: &str, data: FontData) {
+    let families = [FontFamily::Proportional, FontFamily::Monospace]
+        .into_iter()
+        .map(|family| InsertFontFamily {
+            family,
+            priority: FontPriority::Lowest,
+        })
+        .collect();
+    ctx.add_font(FontInsert::new(name, data, families));
+}
+
+/// Adds the first CJK font found on the system, then the bundled one.
+pub fn load(ctx: &Context) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match SYSTEM_FONTS
+        .iter()
+        .find_map(|path| std::fs::read(path).ok())
+    {
+        Some(data) => install(ctx, "cjk-system", FontData::from_owned(data)),
+        None => log::info!("No system CJK font found, using the bundled glyphs"),
+    }
+    install(ctx, "cjk", FontData::from_static(BUNDLED));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui::FontId;
+
+    #[test]
+    fn bundled_font_covers_the_translations() {
+        let ctx = Context::default();
+        install(&ctx, "cjk", FontData::from_static(BUNDLED));
+        let text = [
+            include_str!("../locales/zh.toml"),
+            include_str!("../locales/ja.toml"),
+        ]
+        .concat();
+        let _ = ctx.run(Default::default(), |ctx| {
+            let font = FontId::proportional(14.0);
+            let missing: String = ctx.fonts(|fonts| {
+                text.chars()
+                    .filter(|&c| !c.is_control() && !fonts.has_glyph(&font, c))
+                    .collect()
+            });
+            assert_eq!(missing, "");
+        });
+    }
+}
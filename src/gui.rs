@@ -1,271 +1,2557 @@
 use eframe::egui::{self, Pos2};
 use egui::{Align2, Color32, FontId};
+use serde::{Deserialize, Serialize};
 #[cfg(target_arch = "wasm32")]
 use std::pin::Pin;
 use std::sync::{
     Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
     mpsc::{self, Receiver},
 };
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen_futures::spawn_local;
+use web_time::{Duration, Instant};
 
 use crate::{
-    BOARD_E_SIZE, GRID_SIZE,
-    ai::{AI, GameState},
+    ai::{AI, Candidate, GameState, Hint, Outlook, WinLine},
+    clock::{self, Clock, TimeControl},
+    files,
+    i18n::{Language, tr, trf},
+    layout::BoardLayout,
+    notation::{Style, parse_display, parse_notation, to_notation},
+    psq::TimedMove,
+    record::{self, EngineSettings, GameRecord, Player, RecordMove, Termination, Variation},
+    report::{self, Class, MoveReport, win_probability},
+    review::Review,
+    sgf,
+    theme::{Marker, SAFE_COLORS, Theme},
+    weights::{Profile, Weights},
 };
 
+#[cfg(target_arch = "wasm32")]
+const RECORD_KEY_PREFIX: &str = "gobang-rs/game/";
+
 pub const BOARD_SIZE: usize = 15;
 
+const SIDE_PANEL_WIDTH: f32 = 260.0;
+
+/// Seconds the winning line takes to be drawn at the end of a game.
+const SETTLE_ANIMATION: f32 = 0.8;
+
+/// The AI takes a draw unless it expects to score more than this.
+const DRAW_ACCEPTANCE: f32 = 0.55;
+
+/// Plies the AI searches the position in to judge a draw offer.
+const DRAW_DEPTH: usize = 2;
+
+/// Storage key of the settings, in the native config directory or the browser's
+/// `localStorage`.
+const SETTINGS_KEY: &str = "settings";
+
 #[derive(PartialEq, Eq)]
 enum AppState {
     Idle,
     Gaming,
     AIThinking,
     Settlement,
+    Review,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Mode {
+    HumanVsAI,
+    HumanVsHuman,
+    AIVsAI,
+}
+
+impl Mode {
+    const ALL: [Mode; 3] = [Mode::HumanVsAI, Mode::HumanVsHuman, Mode::AIVsAI];
+
+    fn name(self) -> &'static str {
+        match self {
+            Mode::HumanVsAI => "Human vs AI",
+            Mode::HumanVsHuman => "Human vs Human",
+            Mode::AIVsAI => "AI vs AI",
+        }
+    }
+}
+
+/// An action waiting for the player to confirm it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Confirm {
+    NewGame,
+    Resign,
+    /// A draw offered by the side to move, for the other player to accept.
+    Draw,
+}
+
+/// The options of the start screen and the look of the board, kept across games and
+/// sessions.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    mode: Mode,
+    human_black: bool,
+    depth: usize,
+    profile: Profile,
+    white_depth: usize,
+    white_profile: Profile,
+    custom_weights: Option<Weights>,
+    early_draw: bool,
+    exact_five: bool,
+    confirm_moves: bool,
+    hint_limit: usize,
+    hint_moves: usize,
+    analysis: bool,
+    analysis_depth: usize,
+    analysis_top: usize,
+    move_delay: u64,
+    time_control: Option<TimeControl>,
+    notation: Style,
+    /// `None` follows the browser or system language.
+    language: Option<Language>,
+    theme: Theme,
+    /// Themes loaded from files.
+    themes: Vec<Theme>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        GobangApp::new().settings()
+    }
 }
 
 pub struct GobangApp {
     board: [[i32; BOARD_SIZE]; BOARD_SIZE],
+    /// The game's engine. It plays the AI's side, white in a human vs human game and
+    /// black in an AI vs AI game.
     ai: Arc<Mutex<AI>>,
+    /// White's engine in an AI vs AI game, following the same moves.
+    opponent: Option<Arc<Mutex<AI>>>,
     state: AppState,
     last_step: Option<(usize, usize)>,
-    rx: Option<Receiver<(usize, usize)>>,
+    outlook: Option<Outlook>,
+    /// The AI's move and its score.
+    rx: Option<Receiver<((usize, usize), i32)>>,
+    search_cancel: Option<Arc<AtomicBool>>,
+    confirm: Option<Confirm>,
+    /// Ply at which the AI declined a draw; it is not asked again before the next move.
+    draw_declined: Option<usize>,
+    /// Ply of a draw offer the AI is weighing and the offering side's score it finds,
+    /// `None` if there is nothing to search.
+    draw_rx: Option<(usize, Receiver<Option<i32>>)>,
+    hint: Option<Hint>,
+    /// Intersection clicked once in confirm mode, played when clicked again.
+    pending: Option<(usize, usize)>,
+    hint_rx: Option<Receiver<Hint>>,
+    /// Move number and suggested cell of every hint given this game.
+    hints: Vec<(usize, (usize, usize))>,
+    analysis: bool,
+    /// Move count of the position being analysed, if an analysis is running.
+    analysis_ply: Option<usize>,
+    analysis_cancel: Option<Arc<AtomicBool>>,
+    analysis_rx: Option<Receiver<Vec<Candidate>>>,
+    candidates: Vec<Candidate>,
+    /// Time each move took, in milliseconds.
+    move_times: Vec<u64>,
+    move_clock: Instant,
+    started_at: u64,
+    /// Number of setup stones at the start of the engine's move list.
+    setup: usize,
+    /// Whether black played the first move after the setup stones.
+    black_first: bool,
+    comment: Option<String>,
+    /// Main-line move index and comment, kept from a loaded record.
+    move_comments: Vec<(usize, String)>,
+    /// Alternative lines of the main line, loaded or saved from the analysis.
+    variations: Vec<Variation>,
+    review: Option<Review>,
+    /// Black's and white's clocks in a timed game.
+    clocks: Option<[Clock; 2]>,
+    termination: Option<Termination>,
+    win_line: Option<WinLine>,
+    ended_at: Option<Instant>,
+    /// Post-game analysis, one entry per main-line move, filled in as it runs.
+    report: Vec<MoveReport>,
+    report_rx: Option<Receiver<MoveReport>>,
+    report_cancel: Option<Arc<AtomicBool>>,
 
     // Config
-    role: &'static str,
+    mode: Mode,
     role_black: bool,
     depth: usize,
+    early_draw: bool,
+    /// Only exactly five in a row wins (standard gomoku), not an overline.
+    exact_five: bool,
+    /// A move takes two clicks on the same intersection, for touch screens.
+    confirm_moves: bool,
+    hint_limit: usize,
+    /// Number of moves a hint suggests, the best one highlighted.
+    hint_moves: usize,
+    analysis_depth: usize,
+    analysis_top: usize,
+    profile: Profile,
+    /// White's engine in an AI vs AI game; `depth` and `profile` configure black's.
+    white_depth: usize,
+    white_profile: Profile,
+    /// Least time between the moves of an AI vs AI game, in milliseconds.
+    move_delay: u64,
+    paused: bool,
+    time_control: Option<TimeControl>,
+    custom_weights: Option<Weights>,
+    weights_rx: Option<Receiver<(String, Vec<u8>)>>,
+    weights_error: Option<String>,
+    record_rx: Option<Receiver<(String, Vec<u8>)>>,
+    record_error: Option<String>,
+    /// Position string typed or pasted on the start screen, e.g. `h8i9j10`.
+    position_text: String,
+    /// Move typed in the side panel, e.g. `h8`.
+    move_text: String,
+    move_error: Option<String>,
+    /// The keyboard cursor on the board, shown once an arrow key has been pressed.
+    cursor: Option<(usize, usize)>,
+
+    // Look
+    notation: Style,
+    /// The chosen language, `None` to detect it.
+    language: Option<Language>,
+    theme: Theme,
+    /// The built-in themes followed by those loaded from files.
+    themes: Vec<Theme>,
+    theme_rx: Option<Receiver<(String, Vec<u8>)>>,
+    theme_error: Option<String>,
+    settings_open: bool,
 
     // Others
+    /// Where the board was painted last frame, for the panels to hit-test the pointer.
+    layout: Option<BoardLayout>,
     last_frame_time: f64,
     #[cfg(target_arch = "wasm32")]
-    lazy_task: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    lazy_tasks: Vec<Pin<Box<dyn Future<Output = ()> + Send>>>,
 }
 impl GobangApp {
     pub fn new() -> Self {
         Self {
             board: [[0; BOARD_SIZE]; BOARD_SIZE],
             ai: Arc::new(Mutex::new(AI::new())),
+            opponent: None,
             state: AppState::Idle,
             last_step: None,
+            outlook: None,
             rx: None,
+            search_cancel: None,
+            confirm: None,
+            draw_declined: None,
+            draw_rx: None,
+            hint: None,
+            pending: None,
+            hint_rx: None,
+            hints: Vec::new(),
+            analysis: false,
+            analysis_ply: None,
+            analysis_cancel: None,
+            analysis_rx: None,
+            candidates: Vec::new(),
+            move_times: Vec::new(),
+            move_clock: Instant::now(),
+            started_at: record::now(),
+            setup: 0,
+            black_first: true,
+            comment: None,
+            move_comments: Vec::new(),
+            variations: Vec::new(),
+            review: None,
+            clocks: None,
+            termination: None,
+            win_line: None,
+            ended_at: None,
+            report: Vec::new(),
+            report_rx: None,
+            report_cancel: None,
 
-            role: "BLACK",
+            mode: Mode::HumanVsAI,
             role_black: true,
             depth: 2,
+            early_draw: true,
+            exact_five: false,
+            confirm_moves: false,
+            hint_limit: 3,
+            hint_moves: 1,
+            analysis_depth: 3,
+            analysis_top: 5,
+            profile: Profile::Balanced,
+            white_depth: 2,
+            white_profile: Profile::Balanced,
+            move_delay: 500,
+            paused: false,
+            time_control: None,
+            custom_weights: None,
+            weights_rx: None,
+            weights_error: None,
+            record_rx: None,
+            record_error: None,
+            position_text: String::new(),
+            move_text: String::new(),
+            move_error: None,
+            cursor: None,
+
+            notation: Style::Standard,
+            language: None,
+            theme: Theme::builtin().remove(0),
+            themes: Theme::builtin(),
+            theme_rx: None,
+            theme_error: None,
+            settings_open: false,
 
+            layout: None,
             last_frame_time: 0.0,
             #[cfg(target_arch = "wasm32")]
-            lazy_task: None,
+            lazy_tasks: Vec::new(),
         }
     }
-}
-impl eframe::App for GobangApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let frame_time = ctx.input(|i| i.time);
-            let fps = if frame_time - self.last_frame_time > 0.0 {
-                1.0 / (frame_time - self.last_frame_time)
-            } else {
-                0.0
+
+    /// Runs `task` off the UI: on a tokio worker natively, or on the next frame on the web
+    /// so that the current frame is painted before the task blocks the page.
+    fn spawn_task(&mut self, task: impl Future<Output = ()> + Send + 'static) {
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.lazy_tasks.push(Box::pin(task));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            tokio::task::spawn(task);
+        }
+    }
+
+    fn weights(&self) -> Weights {
+        self.profile_weights(self.profile)
+    }
+
+    fn profile_weights(&self, profile: Profile) -> Weights {
+        match profile {
+            Profile::Custom => self.custom_weights.clone().unwrap_or_default(),
+            profile => profile.weights(),
+        }
+    }
+
+    fn engine(&self, depth: usize, weights: Weights, black: bool) -> Arc<Mutex<AI>> {
+        let mut ai = AI::new();
+        ai.depth = depth;
+        ai.early_draw = self.early_draw;
+        ai.exact_five = self.exact_five;
+        ai.ai_black = black;
+        ai.set_weights(weights);
+        Arc::new(Mutex::new(ai))
+    }
+
+    /// Sets up fresh engines for the current mode and settings.
+    fn start_engines(&mut self) {
+        let ai_black = match self.mode {
+            Mode::HumanVsAI => !self.role_black,
+            Mode::HumanVsHuman => false,
+            Mode::AIVsAI => true,
+        };
+        self.ai = self.engine(self.depth, self.weights(), ai_black);
+        self.opponent = (self.mode == Mode::AIVsAI).then(|| {
+            let weights = self.profile_weights(self.white_profile);
+            self.engine(self.white_depth, weights, false)
+        });
+    }
+
+    /// Counted from the moves on the board, so that it does not wait for a searching
+    /// engine.
+    fn black_to_move(&self) -> bool {
+        (self.move_times.len() - self.setup).is_multiple_of(2) == self.black_first
+    }
+
+    fn new_clocks(&self) -> Option<[Clock; 2]> {
+        self.time_control.map(|control| [Clock::new(control); 2])
+    }
+
+    /// Whether the side to move is thinking on its clock; an AI vs AI game waiting out
+    /// the move delay is not.
+    fn clock_running(&self) -> bool {
+        self.clocks.is_some()
+            && (self.state == AppState::AIThinking
+                || (self.state == AppState::Gaming && self.is_human(self.black_to_move())))
+    }
+
+    /// Ends the game off the board, `winner` being black, white or, if `None`, neither.
+    fn end_game(&mut self, termination: Termination, winner: Option<bool>) {
+        {
+            let mut ai = self.ai.lock().unwrap();
+            ai.state = match winner {
+                None => GameState::Draw,
+                Some(black) if black == ai.ai_black => GameState::AI,
+                Some(_) => GameState::Human,
             };
-            self.last_frame_time = frame_time;
-            if self.state == AppState::Idle {
-                ui.vertical_centered(|ui| {
-                    ui.label(format!("FPS: {:.2}", fps));
-                });
-                ui.label("You");
-                if ui.button(self.role).clicked() {
-                    self.role = if self.role == "BLACK" {
-                        "WHITE"
-                    } else {
-                        "BLACK"
-                    }
+        }
+        self.termination = Some(termination);
+        self.settle();
+    }
+
+    fn settle(&mut self) {
+        self.state = AppState::Settlement;
+        self.win_line = self.ai.lock().unwrap().winning_line();
+        self.ended_at = Some(Instant::now());
+        self.hint = None;
+        self.stop_analysis();
+    }
+
+    fn is_human(&self, black: bool) -> bool {
+        match self.mode {
+            Mode::HumanVsAI => black == self.role_black,
+            Mode::HumanVsHuman => true,
+            Mode::AIVsAI => false,
+        }
+    }
+
+    /// A copy of the game's engine that plays `black`'s side, for hints and analysis.
+    fn engine_for(&self, black: bool) -> AI {
+        let ai = self.ai.lock().unwrap();
+        let mut engine = if ai.ai_black == black {
+            ai.clone()
+        } else {
+            ai.for_human()
+        };
+        // The AI's clock does not limit helpers.
+        engine.time_limit = None;
+        engine
+    }
+
+    /// Plays `(x, y)` for `black`'s side on every engine of the game.
+    fn place(&mut self, (x, y): (usize, usize), black: bool) {
+        for engine in [Some(&self.ai), self.opponent.as_ref()]
+            .into_iter()
+            .flatten()
+        {
+            let mut engine = engine.lock().unwrap();
+            if black == engine.ai_black {
+                engine.ai_step(x, y);
+            } else {
+                engine.human_step(x, y);
+            }
+        }
+    }
+
+    /// Ends the game if it is over, otherwise hands the move to the side to play it. AI vs
+    /// AI moves are started from `update` once the move delay has passed.
+    fn next_turn(&mut self) {
+        if self.termination.is_some() || self.ai.lock().unwrap().is_game_over() {
+            self.settle();
+        } else if self.is_human(self.black_to_move()) || self.mode == Mode::AIVsAI {
+            self.state = AppState::Gaming;
+        } else {
+            self.start_ai_move();
+        }
+    }
+
+    /// Puts a stone on the board and charges the time since the previous move to it,
+    /// ending the game if the mover's flag has fallen.
+    fn record_step(&mut self, (x, y): (usize, usize), black: bool) {
+        self.board[x][y] = if black { 1 } else { 2 };
+        self.last_step = Some((x, y));
+        let elapsed = self.move_clock.elapsed().as_millis() as u64;
+        self.move_times.push(elapsed);
+        self.move_clock = Instant::now();
+        if let Some(clocks) = &mut self.clocks
+            && !clocks[usize::from(!black)].punch(elapsed)
+        {
+            self.end_game(Termination::Time, Some(!black));
+        }
+    }
+
+    fn start_ai_move(&mut self) {
+        let (engine, mirror) = match &self.opponent {
+            Some(opponent) if !self.black_to_move() => (opponent.clone(), Some(self.ai.clone())),
+            Some(opponent) => (self.ai.clone(), Some(opponent.clone())),
+            None => (self.ai.clone(), None),
+        };
+        let time_limit = self
+            .clocks
+            .map(|clocks| clocks[usize::from(!self.black_to_move())].budget());
+        let (tx, rx) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.rx = Some(rx);
+        self.search_cancel = Some(cancel.clone());
+        self.state = AppState::AIThinking;
+        self.move_clock = Instant::now();
+        self.spawn_task(async move {
+            let mut engine = engine.lock().unwrap();
+            engine.time_limit = time_limit;
+            engine.cancel = Some(cancel);
+            // The game is over before the board fills up.
+            let step = engine.ai().expect("no empty cell left for the AI");
+            let score = engine.last_score;
+            drop(engine);
+            if let Some(mirror) = mirror {
+                mirror.lock().unwrap().human_step(step.0, step.1);
+            }
+            // Nobody is waiting for the move if the game was abandoned.
+            tx.send((step, score)).ok();
+        });
+    }
+
+    /// The rule as saved in records.
+    fn rule(&self) -> &'static str {
+        if self.exact_five {
+            "standard"
+        } else {
+            "freestyle"
+        }
+    }
+
+    fn engine_settings(&self, depth: usize, profile: Profile) -> EngineSettings {
+        EngineSettings {
+            depth,
+            profile: profile.name().to_string(),
+            weights: self.profile_weights(profile),
+            early_draw: self.early_draw,
+        }
+    }
+
+    fn to_record(&self) -> GameRecord {
+        let player = |black| {
+            if self.is_human(black) {
+                Player::Human
+            } else {
+                Player::AI
+            }
+        };
+        let ai = self.ai.lock().unwrap();
+        let (setup_black, setup_white): (Vec<_>, Vec<_>) = ai.all_steps[..self.setup]
+            .iter()
+            .partition(|&&(x, y)| self.board[x][y] == 1);
+        let balanced = setup_black.len() == setup_white.len();
+        GameRecord {
+            black: player(true),
+            white: player(false),
+            rule: self.rule().to_string(),
+            engine: self.engine_settings(self.depth, self.profile),
+            white_engine: (self.mode == Mode::AIVsAI)
+                .then(|| self.engine_settings(self.white_depth, self.white_profile)),
+            date: self.started_at,
+            result: result(&ai).map(str::to_string),
+            termination: self.termination,
+            clocks: self.clocks,
+            moves: ai.all_steps[self.setup..]
+                .iter()
+                .zip(&self.move_times[self.setup..])
+                .enumerate()
+                .map(|(i, (&step, &time_ms))| RecordMove {
+                    step: to_notation(step),
+                    time_ms,
+                    comment: self
+                        .move_comments
+                        .iter()
+                        .find(|&&(ply, _)| ply == i)
+                        .map(|(_, comment)| comment.clone()),
+                })
+                .collect(),
+            hints: self
+                .hints
+                .iter()
+                .map(|&(ply, step)| (ply, to_notation(step)))
+                .collect(),
+            setup_black: setup_black.into_iter().map(|&s| to_notation(s)).collect(),
+            setup_white: setup_white.into_iter().map(|&s| to_notation(s)).collect(),
+            black_first: (self.black_first != balanced).then_some(self.black_first),
+            comment: self.comment.clone(),
+            variations: self.variations.clone(),
+            report: self.report.clone(),
+        }
+    }
+
+    /// The app as it starts up, with the settings of the last session.
+    pub fn restore(storage: Option<&dyn eframe::Storage>) -> Self {
+        let mut app = GobangApp::new();
+        let saved = storage.and_then(|storage| storage.get_string(SETTINGS_KEY));
+        let settings = match saved.map(|json| serde_json::from_str(&json)) {
+            Some(Ok(settings)) => settings,
+            Some(Err(e)) => {
+                log::warn!("Can not restore the settings: {}", e);
+                Settings::default()
+            }
+            None => Settings::default(),
+        };
+        app.apply(settings);
+        app
+    }
+
+    fn settings(&self) -> Settings {
+        let builtin = Theme::builtin();
+        Settings {
+            mode: self.mode,
+            human_black: self.role_black,
+            depth: self.depth,
+            profile: self.profile,
+            white_depth: self.white_depth,
+            white_profile: self.white_profile,
+            custom_weights: self.custom_weights.clone(),
+            early_draw: self.early_draw,
+            exact_five: self.exact_five,
+            confirm_moves: self.confirm_moves,
+            hint_limit: self.hint_limit,
+            hint_moves: self.hint_moves,
+            analysis: self.analysis,
+            analysis_depth: self.analysis_depth,
+            analysis_top: self.analysis_top,
+            move_delay: self.move_delay,
+            time_control: self.time_control,
+            notation: self.notation,
+            language: self.language,
+            theme: self.theme.clone(),
+            themes: self
+                .themes
+                .iter()
+                .filter(|t| !builtin.contains(t))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn apply(&mut self, settings: Settings) {
+        self.mode = settings.mode;
+        self.role_black = settings.human_black;
+        self.depth = settings.depth;
+        // A custom style needs its weights.
+        let custom = |profile| match profile {
+            Profile::Custom if settings.custom_weights.is_none() => Profile::Balanced,
+            profile => profile,
+        };
+        self.profile = custom(settings.profile);
+        self.white_profile = custom(settings.white_profile);
+        self.white_depth = settings.white_depth;
+        self.custom_weights = settings.custom_weights;
+        self.early_draw = settings.early_draw;
+        self.exact_five = settings.exact_five;
+        self.confirm_moves = settings.confirm_moves;
+        self.hint_limit = settings.hint_limit;
+        self.hint_moves = settings.hint_moves;
+        self.analysis = settings.analysis;
+        self.analysis_depth = settings.analysis_depth;
+        self.analysis_top = settings.analysis_top;
+        self.move_delay = settings.move_delay;
+        self.time_control = settings.time_control;
+        self.notation = settings.notation;
+        self.language = settings.language;
+        self.language.unwrap_or_else(Language::detect).set();
+        for theme in settings.themes {
+            self.add_theme(theme);
+        }
+        self.theme = settings.theme;
+    }
+
+    /// Adds a theme to the choices, replacing any of the same name.
+    fn add_theme(&mut self, theme: Theme) {
+        self.themes.retain(|t| t.name != theme.name);
+        self.themes.push(theme);
+    }
+
+    /// A new game with the settings of this one.
+    fn fresh(&self) -> Self {
+        let mut app = GobangApp::new();
+        app.apply(self.settings());
+        app
+    }
+
+    /// The game being played, or the reviewed tree with the lines explored in review.
+    fn current_record(&self) -> GameRecord {
+        match &self.review {
+            Some(review) => review.record.clone(),
+            None => self.to_record(),
+        }
+    }
+
+    fn save_record(&self) {
+        let record = self.current_record();
+        #[cfg(target_arch = "wasm32")]
+        files::storage::set(
+            &format!(
+                "{}{} {}",
+                RECORD_KEY_PREFIX,
+                record::format_date(record.date),
+                record.result.as_deref().unwrap_or("*")
+            ),
+            &record.to_json(),
+        );
+        files::save_file(record.file_name(), record.to_json().into_bytes());
+    }
+
+    /// Replaces the current game with `record`, continuing it if it is unfinished.
+    fn load_record(&mut self, record: GameRecord) -> Result<(), String> {
+        let opening = record.opening()?;
+        let black_first = record.black_first();
+        let steps = record
+            .steps()
+            .map_err(|step| format!("malformed move {:?}", step))?;
+        let moves = steps
+            .into_iter()
+            .enumerate()
+            .map(|(i, step)| (step, i.is_multiple_of(2) == black_first));
+        let stones: Vec<_> = opening.iter().copied().chain(moves).collect();
+        let mut app = self.fresh();
+        app.mode = match (record.black, record.white) {
+            (Player::Human, Player::Human) => Mode::HumanVsHuman,
+            (Player::AI, Player::AI) => Mode::AIVsAI,
+            _ => Mode::HumanVsAI,
+        };
+        app.role_black = record.black == Player::Human;
+        app.early_draw = record.engine.early_draw;
+        app.exact_five = record.rule == "standard";
+        let profile = |engine: &EngineSettings| {
+            Profile::BUILTIN
+                .into_iter()
+                .find(|p| p.name() == engine.profile && p.weights() == engine.weights)
+                .unwrap_or(Profile::Custom)
+        };
+        app.depth = record.engine.depth;
+        app.profile = profile(&record.engine);
+        app.custom_weights = Some(record.engine.weights.clone());
+        app.started_at = record.date;
+        if let Some(white) = &record.white_engine {
+            app.white_depth = white.depth;
+            app.white_profile = profile(white);
+        }
+        app.start_engines();
+        // The recorded weights win over the profiles, which may have changed since.
+        app.ai
+            .lock()
+            .unwrap()
+            .set_weights(record.engine.weights.clone());
+        if let (Some(opponent), Some(white)) = (&app.opponent, &record.white_engine) {
+            opponent.lock().unwrap().set_weights(white.weights.clone());
+        }
+        for ((x, y), black) in stones {
+            if x >= BOARD_SIZE || y >= BOARD_SIZE || app.board[x][y] != 0 {
+                return Err(format!("illegal move {}", self.notation.display((x, y))));
+            }
+            if app.ai.lock().unwrap().is_game_over() {
+                return Err("moves continue after the game ended".to_string());
+            }
+            app.place((x, y), black);
+            app.record_step((x, y), black);
+        }
+        app.setup = opening.len();
+        app.black_first = black_first;
+        app.move_times = vec![0; opening.len()];
+        app.move_times
+            .extend(record.moves.iter().map(|m| m.time_ms));
+        app.comment = record.comment.clone();
+        app.move_comments = record
+            .moves
+            .iter()
+            .enumerate()
+            .filter_map(|(i, m)| Some((i, m.comment.clone()?)))
+            .collect();
+        app.variations = record.variations.clone();
+        app.report = record.report.clone();
+        app.hints = record
+            .hints
+            .iter()
+            .filter_map(|(ply, step)| Some((*ply, parse_notation(step)?)))
+            .collect();
+        // Replaying the moves must not charge the clocks.
+        app.clocks = record.clocks;
+        app.time_control = record.clocks.map(|clocks| clocks[0].control);
+        *self = app;
+        match record.termination {
+            Some(termination) if record.result.is_some() => {
+                let winner = match record.result.as_deref() {
+                    Some("1-0") => Some(true),
+                    Some("0-1") => Some(false),
+                    _ => None,
+                };
+                self.end_game(termination, winner);
+            }
+            _ => self.next_turn(),
+        }
+        Ok(())
+    }
+
+    /// Continues a game from another program with the current settings, the human taking
+    /// the side to move.
+    fn import_game(&mut self, moves: Vec<TimedMove>) -> Result<(), String> {
+        let human_black = moves.len().is_multiple_of(2);
+        let record = GameRecord {
+            black: if human_black {
+                Player::Human
+            } else {
+                Player::AI
+            },
+            white: if human_black {
+                Player::AI
+            } else {
+                Player::Human
+            },
+            date: record::now(),
+            result: None,
+            termination: None,
+            clocks: self.new_clocks(),
+            moves: moves
+                .into_iter()
+                .map(|(step, time_ms)| RecordMove {
+                    step: to_notation(step),
+                    time_ms,
+                    comment: None,
+                })
+                .collect(),
+            hints: Vec::new(),
+            setup_black: Vec::new(),
+            setup_white: Vec::new(),
+            black_first: None,
+            variations: Vec::new(),
+            report: Vec::new(),
+            ..self.to_record()
+        };
+        self.load_record(record)
+    }
+
+    /// The review panel: move list, navigation buttons, the timeline and the lines
+    /// branching off at the current move.
+    fn show_review(&mut self, ui: &mut egui::Ui) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        let last = review.last_ply();
+        let mut close = false;
+        let mut play_from_here = false;
+        ui.heading(tr("Review"));
+        ui.horizontal(|ui| {
+            if ui.button(tr("|<")).clicked() {
+                review.go_to(0);
+            }
+            if ui.button(tr("<")).clicked() {
+                review.go_to(review.ply.saturating_sub(1));
+            }
+            if ui.button(tr(">")).clicked() {
+                review.go_to(review.ply + 1);
+            }
+            if ui.button(tr(">|")).clicked() {
+                review.go_to(last);
+            }
+        });
+        let mut ply = review.ply;
+        if ui
+            .add(egui::Slider::new(&mut ply, 0..=last).text(tr("move")))
+            .changed()
+        {
+            review.go_to(ply);
+        }
+        let line = review.line();
+        let report = if review.on_main_line() {
+            review.record.report.clone()
+        } else {
+            Vec::new()
+        };
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for (pair, moves) in line.chunks(2).enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}.", pair + 1));
+                        for (i, m) in moves.iter().enumerate() {
+                            let ply = pair * 2 + i + 1;
+                            let mark = match report.get(ply - 1).map(|r| r.class) {
+                                Some(Class::Inaccuracy) => "?!",
+                                Some(Class::Mistake) => "?",
+                                Some(Class::Blunder) => "??",
+                                _ => "",
+                            };
+                            let label =
+                                format!("{}{}", self.notation.display_notation(&m.step), mark);
+                            if ui.selectable_label(review.ply == ply, label).clicked() {
+                                review.go_to(ply);
+                            }
+                        }
+                    });
                 }
-                ui.add(egui::Slider::new(&mut self.depth, 1..=4).text("recurse depth"));
-                if ui.button("Start Game").clicked() {
-                    self.state = AppState::Gaming;
-                    self.ai.lock().unwrap().depth = self.depth;
-                    self.ai.lock().unwrap().ai_black = !self.role_black;
-                    if self.role == "BLACK" {
-                        self.role_black = true;
-                    } else {
-                        self.role_black = false;
-                        self.board[7][7] = 1;
-                        self.ai.lock().unwrap().ai_step(7, 7);
-                        self.last_step = Some((7, 7));
+            });
+        if let Some(comment) = review
+            .ply
+            .checked_sub(1)
+            .and_then(|i| line[i].comment.as_ref())
+        {
+            ui.label(comment);
+        }
+        let alternatives = review.alternatives();
+        if !alternatives.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                ui.label(tr("Variations:"));
+                for (path, first) in alternatives {
+                    if ui.button(self.notation.display_notation(&first)).clicked() {
+                        review.select(path);
+                        review.go_to(review.ply + 1);
                     }
                 }
-                return;
+            });
+        }
+        ui.label(tr("Click the board to try another move here."));
+        ui.horizontal(|ui| {
+            play_from_here = ui.button(tr("Play from here")).clicked();
+            close = ui.button(tr("Close")).clicked();
+        });
+        if play_from_here {
+            let record = review.position();
+            if let Err(e) = self.load_record(record) {
+                log::error!("Can not continue from here: {}", e);
             }
-            #[cfg(target_arch = "wasm32")]
-            if self.lazy_task.is_some() {
-                spawn_local(self.lazy_task.take().unwrap());
-            }
-            let board_e_size = *BOARD_E_SIZE.lock().unwrap();
-            let grid_size = *GRID_SIZE.lock().unwrap();
-            let (_, response) = ui.allocate_at_least(
-                egui::Vec2::new(board_e_size, board_e_size),
-                egui::Sense::click(),
-            );
-            let painter = ui.painter();
-            painter.rect_filled(ui.clip_rect(), 0.0, Color32::from_rgb(239, 228, 176));
-            for i in 0..BOARD_SIZE {
-                let start = Pos2::new(0.0, 0.0)
-                    + egui::Vec2::new((grid_size * (i + 1)) as f32, grid_size as f32);
-                let end = Pos2::new(0.0, 0.0)
-                    + egui::Vec2::new(
-                        (grid_size * (i + 1)) as f32,
-                        board_e_size - grid_size as f32,
-                    );
-                painter.line_segment([start, end], egui::Stroke::new(1.0, egui::Color32::BLACK));
-            }
-            for i in 0..BOARD_SIZE {
-                let start = Pos2::new(0.0, 0.0)
-                    + egui::Vec2::new(grid_size as f32, (grid_size * (i + 1)) as f32);
-                let end = Pos2::new(0.0, 0.0)
-                    + egui::Vec2::new(
-                        board_e_size - grid_size as f32,
-                        (grid_size * (i + 1)) as f32,
+        } else if close {
+            if let Some(review) = self.review.take() {
+                self.variations = review.record.variations;
+            }
+            self.state = AppState::Settlement;
+        }
+    }
+
+    /// Re-analyses every move of the game in the background at the analysis depth,
+    /// publishing one report per move.
+    fn start_report(&mut self) {
+        let record = self.to_record();
+        let (Ok(opening), Ok(steps)) = (record.opening(), record.steps()) else {
+            return;
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        let (depth, weights, exact_five) = (self.analysis_depth, self.weights(), self.exact_five);
+        let black_first = record.black_first();
+        self.report.clear();
+        self.report_rx = Some(rx);
+        self.report_cancel = Some(cancel.clone());
+        self.spawn_task(async move {
+            let mut stones = opening;
+            for (i, step) in steps.into_iter().enumerate() {
+                let black = i.is_multiple_of(2) == black_first;
+                let Some(report) = report::analyse_move(
+                    &stones, step, black, depth, exact_five, &weights, &cancel,
+                ) else {
+                    break;
+                };
+                if tx.send(report).is_err() {
+                    break;
+                }
+                stones.push((step, black));
+                yield_to_page().await;
+            }
+        });
+    }
+
+    fn stop_report(&mut self) {
+        if let Some(cancel) = self.report_cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.report_rx = None;
+    }
+
+    /// The settlement report: per-side summary, the evaluation graph and the flagged
+    /// moves.
+    fn show_report(&mut self, ui: &mut egui::Ui) {
+        if self.report.is_empty() && self.report_rx.is_none() {
+            return;
+        }
+        let ai = self.ai.lock().unwrap();
+        let moves = &ai.all_steps[self.setup..];
+        // Whether the `i`-th move of the main line is black's.
+        let black = |i: usize| i.is_multiple_of(2) == self.black_first;
+        ui.heading(tr("Report"));
+        if self.report_rx.is_some() {
+            ui.label(trf(
+                "Analysing move {0} of {1}...",
+                &[&(self.report.len() + 1), &moves.len()],
+            ));
+        }
+        egui::Grid::new("report summary").show(ui, |ui| {
+            ui.label("");
+            for class in [Class::Inaccuracy, Class::Mistake, Class::Blunder] {
+                ui.label(tr(class.name()));
+            }
+            ui.label(tr("missed wins"));
+            ui.label(tr("missed defences"));
+            ui.end_row();
+            for (side, is_black) in [("Black", true), ("White", false)] {
+                let mine = || {
+                    self.report
+                        .iter()
+                        .enumerate()
+                        .filter(move |&(i, _)| black(i) == is_black)
+                        .map(|(_, r)| r)
+                };
+                ui.label(tr(side));
+                for class in [Class::Inaccuracy, Class::Mistake, Class::Blunder] {
+                    ui.label(mine().filter(|r| r.class == class).count().to_string());
+                }
+                ui.label(mine().filter(|r| r.missed_win).count().to_string());
+                ui.label(mine().filter(|r| r.missed_defence).count().to_string());
+                ui.end_row();
+            }
+        });
+        // Black's expected result after every move.
+        let (response, painter) = ui.allocate_painter(
+            egui::Vec2::new(ui.available_width(), 80.0),
+            egui::Sense::hover(),
+        );
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, Color32::from_gray(60));
+        painter.line_segment(
+            [rect.left_center(), rect.right_center()],
+            egui::Stroke::new(1.0, Color32::GRAY),
+        );
+        let step = rect.width() / moves.len().max(1) as f32;
+        let points: Vec<Pos2> = self
+            .report
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let p = win_probability(r.score);
+                let p = if black(i) { p } else { 1.0 - p };
+                Pos2::new(
+                    rect.left() + step * (i + 1) as f32,
+                    rect.bottom() - rect.height() * p,
+                )
+            })
+            .collect();
+        painter.add(egui::Shape::line(
+            points.clone(),
+            egui::Stroke::new(1.5, Color32::WHITE),
+        ));
+        for (point, r) in points.iter().zip(&self.report) {
+            let color = match r.class {
+                Class::Blunder => Color32::RED,
+                Class::Mistake => Color32::from_rgb(255, 140, 0),
+                Class::Inaccuracy => Color32::YELLOW,
+                _ => continue,
+            };
+            painter.circle_filled(*point, 3.0, color);
+        }
+        egui::ScrollArea::vertical()
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for (i, r) in self.report.iter().enumerate() {
+                    let flagged =
+                        matches!(r.class, Class::Inaccuracy | Class::Mistake | Class::Blunder)
+                            || r.missed_win
+                            || r.missed_defence;
+                    if !flagged {
+                        continue;
+                    }
+                    let mut text = trf(
+                        "{0}. {1} {2}, best {3} ({4})",
+                        &[
+                            &(i + 1),
+                            &self.notation.display(moves[i]),
+                            &tr(r.class.name()),
+                            &self.notation.display_notation(&r.best),
+                            &format_score(r.best_score),
+                        ],
                     );
-                painter.line_segment([start, end], egui::Stroke::new(1.0, egui::Color32::BLACK));
-            }
-            if response.clicked() {
-                if self.state == AppState::Gaming {
-                    if let Some(pos) = response.interact_pointer_pos() {
-                        let (y, x) = (pos.x as usize, pos.y as usize);
-                        if !(x % grid_size > grid_size / 4 && x % grid_size < grid_size / 4 * 3)
-                            || (y % grid_size > grid_size / 4 && y % grid_size < grid_size / 4 * 3)
-                        {
-                            let (x, y) = (
-                                (x - grid_size / 2) / grid_size,
-                                (y - grid_size / 2) / grid_size,
-                            );
-                            if (x < BOARD_SIZE || y < BOARD_SIZE) && self.board[x][y] == 0 {
-                                self.board[x][y] = if self.role_black { 1 } else { 2 };
-                                self.last_step = Some((x, y));
-                                self.ai.lock().unwrap().human_step(x, y);
-                                if !self.ai.lock().unwrap().is_game_over() {
-                                    let ai = self.ai.clone();
-                                    let (tx, rx) = mpsc::channel();
-                                    self.rx = Some(rx);
-                                    self.state = AppState::AIThinking;
-                                    #[cfg(target_arch = "wasm32")]
-                                    {
-                                        self.lazy_task = Some(Box::pin(async move {
-                                            let (nx, ny) = ai.lock().unwrap().ai();
-                                            tx.send((nx, ny)).expect("Can not send data")
-                                        }));
-                                    }
-                                    #[cfg(not(target_arch = "wasm32"))]
-                                    {
-                                        tokio::task::spawn(async move {
-                                            let (nx, ny) = ai.lock().unwrap().ai();
-                                            tx.send((nx, ny)).expect("Can not send data");
-                                        });
-                                    }
-                                } else {
-                                    self.state = AppState::Settlement;
-                                }
-                            }
-                        }
+                    if r.missed_win {
+                        text += tr(", missed VCF");
                     }
-                } else if self.state == AppState::Settlement
-                    && response.interact_pointer_pos().is_some()
-                {
-                    self.state = AppState::Idle;
-                    *self = GobangApp::new();
+                    if r.missed_defence {
+                        text += tr(", missed defence");
+                    }
+                    ui.label(text);
+                }
+            });
+    }
+
+    /// Analyses the candidate moves of the side to move in the background, deepening one ply at a
+    /// time up to `analysis_depth` and publishing the scores after every iteration.
+    fn start_analysis(&mut self) {
+        self.stop_analysis();
+        let mut engine = self.engine_for(self.black_to_move());
+        let cancel = Arc::new(AtomicBool::new(false));
+        engine.cancel = Some(cancel.clone());
+        let (tx, rx) = mpsc::channel();
+        let depth = self.analysis_depth;
+        self.analysis_ply = Some(engine.all_steps.len());
+        self.analysis_cancel = Some(cancel.clone());
+        self.analysis_rx = Some(rx);
+        self.spawn_task(async move {
+            for depth in 1..=depth {
+                let candidates = engine.analyse(depth);
+                if cancel.load(Ordering::Relaxed) || tx.send(candidates).is_err() {
+                    break;
                 }
+                yield_to_page().await;
             }
-            if let Some(rx) = &self.rx
-                && let Ok((nx, ny)) = rx.try_recv()
+        });
+    }
+
+    fn stop_analysis(&mut self) {
+        if let Some(cancel) = self.analysis_cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.analysis_ply = None;
+        self.analysis_rx = None;
+        self.candidates.clear();
+    }
+
+    /// Collects what the background tasks have delivered, ends the game if the flag of a
+    /// human to move has fallen and starts the next move of an AI vs AI game.
+    fn poll(&mut self) {
+        if let Some(rx) = &self.rx
+            && let Ok((step, score)) = rx.try_recv()
+        {
+            self.rx = None;
+            self.record_step(step, self.black_to_move());
+            self.outlook = Outlook::from_score(score);
+            self.next_turn();
+        }
+        let black = self.black_to_move();
+        if self.state == AppState::Gaming
+            && self.is_human(black)
+            && let Some(clocks) = &self.clocks
+            && clocks[usize::from(!black)].flagged(self.move_clock.elapsed().as_millis() as u64)
+        {
+            self.end_game(Termination::Time, Some(!black));
+        }
+        if self.mode == Mode::AIVsAI
+            && self.state == AppState::Gaming
+            && !self.paused
+            && self.move_clock.elapsed() >= Duration::from_millis(self.move_delay)
+        {
+            self.start_ai_move();
+        }
+        if self.state == AppState::Gaming {
+            self.poll_draw();
+            if let Some(rx) = &self.hint_rx
+                && let Ok(hint) = rx.try_recv()
             {
-                self.board[nx][ny] = if self.role_black { 2 } else { 1 };
-                self.state = AppState::Gaming;
-                self.last_step = Some((nx, ny));
-                if self.ai.lock().unwrap().is_game_over() {
-                    self.state = AppState::Settlement;
+                self.hint_rx = None;
+                self.hints
+                    .push((self.ai.lock().unwrap().all_steps.len(), hint.step));
+                self.hint = Some(hint);
+            }
+            if let Some(rx) = &self.analysis_rx {
+                loop {
+                    match rx.try_recv() {
+                        Ok(candidates) => self.candidates = candidates,
+                        Err(mpsc::TryRecvError::Empty) => break,
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            self.analysis_rx = None;
+                            break;
+                        }
+                    }
                 }
             }
-            for x in 0..BOARD_SIZE {
-                for y in 0..BOARD_SIZE {
-                    let center = Pos2::new(0.0, 0.0)
-                        + egui::Vec2::new(
-                            grid_size as f32 * (y + 1) as f32,
-                            grid_size as f32 * (x + 1) as f32,
-                        );
-                    if self.board[x][y] == 1 {
-                        let fill_color = egui::Color32::BLACK;
-                        painter.circle_filled(center, (grid_size / 3) as f32, fill_color);
-                    } else if self.board[x][y] == 2 {
-                        let fill_color = egui::Color32::WHITE;
-                        painter.circle_filled(center, (grid_size / 3) as f32, fill_color);
-                        painter.circle_stroke(
-                            center,
-                            (grid_size / 3) as f32,
-                            egui::Stroke::new(1.0, egui::Color32::BLACK),
-                        );
+            if !self.analysis {
+                self.stop_analysis();
+            } else if self.analysis_ply != Some(self.ai.lock().unwrap().all_steps.len()) {
+                self.start_analysis();
+            }
+        }
+        if let Some(rx) = &self.report_rx {
+            loop {
+                match rx.try_recv() {
+                    Ok(report) => self.report.push(report),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        self.report_rx = None;
+                        break;
                     }
-                    if let Some((cx, cy)) = self.last_step
-                        && cx == x
-                        && cy == y
-                    {
-                        painter.circle_stroke(
-                            center,
-                            (grid_size / 3) as f32,
-                            egui::Stroke::new(2.0, egui::Color32::RED),
-                        );
+                }
+            }
+        }
+    }
+
+    /// The menu bar: the game menu, undo, hint and settings, and the settlement actions.
+    fn show_toolbar(&mut self, ui: &mut egui::Ui) {
+        // The record is read from the engine, which a search holds on to.
+        let idle_engine = self.state != AppState::AIThinking;
+        egui::MenuBar::new().ui(ui, |ui| {
+            ui.menu_button(tr("Game"), |ui| {
+                if ui.button(tr("New game")).clicked() {
+                    if self.state == AppState::Gaming || self.state == AppState::AIThinking {
+                        self.confirm = Some(Confirm::NewGame);
+                    } else {
+                        self.new_game();
                     }
                 }
+                if ui
+                    .add_enabled(idle_engine, egui::Button::new(tr("Load...")))
+                    .clicked()
+                {
+                    self.pick_record();
+                }
+                ui.separator();
+                ui.add_enabled_ui(idle_engine, |ui| self.show_export_menu(ui));
+            });
+            let undo = self.undo_count();
+            if ui
+                .add_enabled(undo.is_some(), egui::Button::new(tr("Undo")))
+                .clicked()
+                && let Some(n) = undo
+                && let Err(e) = self.undo(n)
+            {
+                log::error!("Can not undo: {}", e);
             }
-            if self.state == AppState::AIThinking {
-                painter.rect_filled(
-                    ui.clip_rect(),
-                    0.0,
-                    egui::Color32::from_rgba_unmultiplied(0, 0, 0, 50),
-                );
-                painter.text(
-                    Pos2::new(board_e_size / 2.0, board_e_size / 2.0),
-                    Align2::CENTER_CENTER,
-                    "AI Thinking",
-                    FontId::proportional(32.0),
-                    Color32::RED,
-                );
-            } else if self.state == AppState::Settlement {
-                let text = match self.ai.lock().unwrap().state {
-                    GameState::Human => Some(format!("HUMAN WINS DEPTH {}", self.depth)),
-                    GameState::AI => Some(format!("AI WINS DEPTH {}", self.depth)),
-                    _ => None,
-                };
-                if let Some(text) = text {
-                    painter.rect_filled(
-                        ui.clip_rect(),
-                        0.0,
-                        egui::Color32::from_rgba_unmultiplied(0, 0, 0, 150),
-                    );
-                    painter.text(
-                        Pos2::new(board_e_size / 2.0, board_e_size / 2.0),
-                        Align2::CENTER_CENTER,
-                        text,
-                        FontId::proportional(32.0),
-                        Color32::RED,
-                    );
-                    painter.text(
-                        Pos2::new(board_e_size / 2.0, board_e_size / 4.0 * 3.0),
-                        Align2::CENTER_CENTER,
-                        "Click anywhere to continue",
-                        FontId::proportional(32.0),
-                        Color32::RED,
-                    );
+            if ui
+                .add_enabled(self.can_hint(), egui::Button::new(self.hint_label()))
+                .clicked()
+            {
+                self.request_hint();
+            }
+            if ui
+                .add_enabled(self.can_resign(), egui::Button::new(tr("Resign")))
+                .clicked()
+            {
+                self.confirm = Some(Confirm::Resign);
+            }
+            let offer = self.can_resign()
+                && self.draw_rx.is_none()
+                && self.draw_declined != Some(self.move_times.len());
+            if ui
+                .add_enabled(offer, egui::Button::new(tr("Offer draw")))
+                .clicked()
+            {
+                self.offer_draw();
+            }
+            if ui.button(tr("Settings")).clicked() {
+                self.settings_open = !self.settings_open;
+            }
+        });
+        if let Some(e) = &self.record_error {
+            ui.colored_label(Color32::RED, e);
+        }
+    }
+
+    fn show_export_menu(&mut self, ui: &mut egui::Ui) {
+        if ui.button(tr("Save")).clicked() {
+            self.save_record();
+        }
+        if ui.button(tr("Export .sgf")).clicked() {
+            let record = self.current_record();
+            let name = record.file_name().replace(".json", ".sgf");
+            files::save_file(name, sgf::write(&record).into_bytes());
+        }
+        if ui.button(tr("Export .psq")).clicked() {
+            let record = self.current_record();
+            if let Ok(content) = record.to_psq() {
+                let name = record.file_name().replace(".json", ".psq");
+                files::save_file(name, content.into_bytes());
+            }
+        }
+        if ui.button(tr("Copy position")).clicked()
+            && let Ok(position) = self.current_record().to_position()
+        {
+            ui.ctx().copy_text(position);
+        }
+    }
+
+    /// Leaves the game for the start menu, keeping the settings.
+    fn new_game(&mut self) {
+        if let Some(cancel) = self.search_cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.stop_analysis();
+        self.stop_report();
+        *self = self.fresh();
+    }
+
+    /// Whether a human is to move in a running game, and so may resign or offer a draw.
+    fn can_resign(&self) -> bool {
+        self.state == AppState::Gaming && self.is_human(self.black_to_move())
+    }
+
+    /// Lets the AI search the current position and take the draw, in `poll_draw`, unless
+    /// it expects to win; against a human the other player is asked.
+    fn offer_draw(&mut self) {
+        if self.mode == Mode::HumanVsHuman {
+            self.confirm = Some(Confirm::Draw);
+            return;
+        }
+        // Searched for the side offering the draw, which is to move.
+        let mut engine = self.engine_for(self.black_to_move());
+        engine.depth = DRAW_DEPTH;
+        let (tx, rx) = mpsc::channel();
+        self.draw_rx = Some((self.move_times.len(), rx));
+        self.spawn_task(async move {
+            let score = engine.multi_pv(1).first().map(|line| line.score);
+            tx.send(score).ok();
+        });
+    }
+
+    /// Answers a pending draw offer once the AI has searched the position it was made in.
+    fn poll_draw(&mut self) {
+        let Some((ply, rx)) = &self.draw_rx else {
+            return;
+        };
+        let Ok(score) = rx.try_recv() else {
+            return;
+        };
+        let ply = *ply;
+        self.draw_rx = None;
+        if ply != self.move_times.len() || self.state != AppState::Gaming {
+            return;
+        }
+        // The offering side's loss is the AI's win.
+        if score.is_some_and(|score| win_probability(-score) <= DRAW_ACCEPTANCE) {
+            self.end_game(Termination::Agreement, None);
+        } else {
+            self.draw_declined = Some(ply);
+        }
+    }
+
+    /// Asks for the pending confirmation and carries the action out if accepted.
+    fn show_confirm(&mut self, ctx: &egui::Context, confirm: Confirm) {
+        let (side, other) = if self.black_to_move() {
+            (tr("Black"), tr("White"))
+        } else {
+            (tr("White"), tr("Black"))
+        };
+        let (question, accept, decline) = match confirm {
+            Confirm::NewGame => (
+                tr("Abandon this game and start a new one?").to_string(),
+                tr("New game"),
+                tr("Cancel"),
+            ),
+            Confirm::Resign => (trf("Resign as {0}?", &[&side]), tr("Resign"), tr("Cancel")),
+            Confirm::Draw => (
+                trf("{0} offers a draw. {1}, do you accept?", &[&side, &other]),
+                tr("Accept"),
+                tr("Decline"),
+            ),
+        };
+        let mut answer = None;
+        let modal = egui::Modal::new(egui::Id::new("confirm")).show(ctx, |ui| {
+            ui.label(question);
+            ui.horizontal(|ui| {
+                if ui.button(accept).clicked() {
+                    answer = Some(true);
+                }
+                if ui.button(decline).clicked() {
+                    answer = Some(false);
                 }
+            });
+        });
+        if modal.should_close() {
+            answer.get_or_insert(false);
+        }
+        let Some(accepted) = answer else {
+            return;
+        };
+        self.confirm = None;
+        match confirm {
+            _ if !accepted => {}
+            Confirm::NewGame => self.new_game(),
+            // The game may have ended while the question was open.
+            Confirm::Resign if self.can_resign() => {
+                let black = self.black_to_move();
+                self.end_game(Termination::Resignation, Some(!black));
             }
-            painter.text(
-                Pos2::new(board_e_size / 2.0, ui.min_rect().y_range().min),
-                Align2::CENTER_CENTER,
-                format!("FPS: {:.2}", fps),
-                FontId::default(),
-                Color32::BLACK,
-            );
+            Confirm::Draw if self.can_resign() => self.end_game(Termination::Agreement, None),
+            Confirm::Resign | Confirm::Draw => {}
+        }
+    }
+
+    /// Number of moves to take back so that a human is to move again, if any may be.
+    fn undo_count(&self) -> Option<usize> {
+        if self.state != AppState::Gaming || self.mode == Mode::AIVsAI {
+            return None;
+        }
+        let plies = self.move_times.len();
+        (1..=plies.saturating_sub(self.setup))
+            .find(|n| self.is_human((plies - n - self.setup).is_multiple_of(2) == self.black_first))
+    }
+
+    /// Takes back the last `n` moves by replaying the game without them.
+    fn undo(&mut self, n: usize) -> Result<(), String> {
+        self.stop_analysis();
+        let mut record = self.to_record();
+        let len = record.moves.len().saturating_sub(n);
+        record.moves.truncate(len);
+        record.variations.retain(|v| v.ply <= len);
+        self.load_record(record)
+    }
+
+    fn can_hint(&self) -> bool {
+        self.state == AppState::Gaming
+            && self.mode != Mode::AIVsAI
+            && self.hints.len() < self.hint_limit
+            && self.hint.is_none()
+            && self.hint_rx.is_none()
+    }
+
+    fn hint_label(&self) -> String {
+        if self.hint_rx.is_some() {
+            tr("Thinking...").to_string()
+        } else {
+            trf(
+                "Hint ({0} left)",
+                &[&self.hint_limit.saturating_sub(self.hints.len())],
+            )
+        }
+    }
+
+    fn request_hint(&mut self) {
+        // Hints are for the engine's human side.
+        let ai = self.engine_for(!self.black_to_move());
+        let (tx, rx) = mpsc::channel();
+        let k = self.hint_moves;
+        self.hint_rx = Some(rx);
+        self.spawn_task(async move {
+            tx.send(ai.hint(k)).ok();
         });
     }
+
+    fn pick_record(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        self.record_rx = Some(rx);
+        files::pick_file(tx, &["json", "sgf", "psq", "txt"]);
+    }
+
+    /// Loads the game file picked with `pick_record` once it has been read.
+    fn poll_record(&mut self) {
+        if let Some(rx) = &self.record_rx
+            && let Ok((name, content)) = rx.try_recv()
+        {
+            self.record_rx = None;
+            let content = String::from_utf8_lossy(&content);
+            let name_lower = name.to_ascii_lowercase();
+            let loaded = if name_lower.ends_with(".json") {
+                GameRecord::parse(&content).and_then(|record| self.load_record(record))
+            } else if name_lower.ends_with(".sgf") {
+                let template = GameRecord {
+                    clocks: self.new_clocks(),
+                    ..self.to_record()
+                };
+                sgf::parse(&content, template).and_then(|record| self.load_record(record))
+            } else {
+                record::import_moves(&name, &content).and_then(|moves| self.import_game(moves))
+            };
+            if let Err(e) = loaded {
+                self.record_error = Some(format!("{}: {}", name, e));
+            }
+        }
+    }
+
+    /// The start menu: every option of the next game, grouped in sections.
+    fn show_start_menu(&mut self, ui: &mut egui::Ui, fps: f64) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading(tr("gobang-rs"));
+                let start = egui::Button::new(egui::RichText::new(tr("Start Game")).size(18.0));
+                if ui.add(start).clicked() {
+                    self.start_game();
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                section(ui, tr("Game mode"), |ui| self.show_mode_settings(ui));
+                section(ui, tr("Rules and opening"), |ui| {
+                    self.show_rule_settings(ui)
+                });
+                section(ui, tr("Difficulty and time"), |ui| {
+                    self.show_difficulty_settings(ui)
+                });
+                section(ui, tr("Assistance"), |ui| self.show_assist_settings(ui));
+                section(ui, tr("Display"), |ui| self.show_display_settings(ui));
+                section(ui, tr("Saved games"), |ui| self.show_load_settings(ui));
+            });
+            ui.weak(format!("FPS: {:.2}", fps));
+        });
+    }
+
+    fn start_game(&mut self) {
+        self.started_at = record::now();
+        self.move_clock = Instant::now();
+        self.start_engines();
+        self.clocks = self.new_clocks();
+        self.next_turn();
+    }
+
+    fn show_mode_settings(&mut self, ui: &mut egui::Ui) {
+        for mode in Mode::ALL {
+            ui.radio_value(&mut self.mode, mode, tr(mode.name()));
+        }
+        if self.mode == Mode::HumanVsAI {
+            ui.horizontal(|ui| {
+                ui.label(tr("You play"));
+                ui.selectable_value(&mut self.role_black, true, tr("Black"));
+                ui.selectable_value(&mut self.role_black, false, tr("White"));
+            });
+        }
+    }
+
+    fn show_rule_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label(trf("Board {0}×{0}", &[&BOARD_SIZE]));
+        ui.radio_value(
+            &mut self.exact_five,
+            false,
+            tr("Five or more in a row wins"),
+        );
+        ui.radio_value(
+            &mut self.exact_five,
+            true,
+            tr("Exactly five wins, not six or more"),
+        );
+        ui.checkbox(&mut self.early_draw, tr("Draw when no five is possible"));
+        ui.label(tr("Opening stones"));
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.position_text)
+                    .hint_text("h8i9j10")
+                    .desired_width(120.0),
+            );
+            if ui.button(tr("Load position")).clicked()
+                && let Err(e) = record::import_moves("", &self.position_text)
+                    .and_then(|moves| self.import_game(moves))
+            {
+                self.record_error = Some(e);
+            }
+        });
+    }
+
+    fn show_difficulty_settings(&mut self, ui: &mut egui::Ui) {
+        let ai_vs_ai = self.mode == Mode::AIVsAI;
+        if self.mode != Mode::HumanVsHuman {
+            ui.add(egui::Slider::new(&mut self.depth, 1..=4).text(if ai_vs_ai {
+                tr("black depth")
+            } else {
+                tr("recurse depth")
+            }));
+        }
+        if ai_vs_ai {
+            ui.add(egui::Slider::new(&mut self.white_depth, 1..=4).text(tr("white depth")));
+        }
+        if let Some(rx) = &self.weights_rx
+            && let Ok((name, content)) = rx.try_recv()
+        {
+            self.weights_rx = None;
+            match Weights::parse(&name, &String::from_utf8_lossy(&content)) {
+                Ok(weights) => {
+                    self.custom_weights = Some(weights);
+                    self.profile = Profile::Custom;
+                    self.weights_error = None;
+                }
+                Err(e) => self.weights_error = Some(format!("{}: {}", name, e)),
+            }
+        }
+        let custom = self.custom_weights.is_some();
+        if self.mode != Mode::HumanVsHuman {
+            let label = if ai_vs_ai { "black style" } else { "AI style" };
+            profile_select(ui, tr(label), &mut self.profile, custom);
+        }
+        if ai_vs_ai {
+            profile_select(ui, tr("white style"), &mut self.white_profile, custom);
+        }
+        if ui.button(tr("Load weights...")).clicked() {
+            let (tx, rx) = mpsc::channel();
+            self.weights_rx = Some(rx);
+            files::pick_file(tx, &["json", "toml"]);
+        }
+        if let Some(e) = &self.weights_error {
+            ui.colored_label(Color32::RED, e);
+        }
+        if ai_vs_ai {
+            ui.add(egui::Slider::new(&mut self.move_delay, 0..=3000).text(tr("move delay (ms)")));
+        }
+        time_control_select(ui, &mut self.time_control);
+    }
+
+    /// Options that may change during a game, also shown in the settings window.
+    fn show_assist_settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.hint_limit, 0..=10).text(tr("hints per game")));
+        ui.add(egui::Slider::new(&mut self.hint_moves, 1..=5).text(tr("moves per hint")));
+        ui.add(egui::Slider::new(&mut self.analysis_depth, 1..=4).text(tr("analysis depth")));
+        ui.add(egui::Slider::new(&mut self.analysis_top, 0..=10).text(tr("numbered moves")));
+        ui.checkbox(&mut self.confirm_moves, tr("Confirm moves (click twice)"));
+    }
+
+    fn show_display_settings(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label(tr("notation"))
+            .selected_text(tr(self.notation.name()))
+            .show_ui(ui, |ui| {
+                for option in Style::ALL {
+                    ui.selectable_value(&mut self.notation, option, tr(option.name()));
+                }
+            });
+        let automatic = tr("Automatic");
+        let language = self.language;
+        egui::ComboBox::from_label(tr("language"))
+            .selected_text(self.language.map_or(automatic, Language::name))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.language, None, automatic);
+                for language in Language::ALL {
+                    ui.selectable_value(&mut self.language, Some(language), language.name());
+                }
+            });
+        if self.language != language {
+            self.language.unwrap_or_else(Language::detect).set();
+        }
+        self.show_theme_settings(ui);
+    }
+
+    fn show_load_settings(&mut self, ui: &mut egui::Ui) {
+        if ui.button(tr("Load game...")).clicked() {
+            self.pick_record();
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut keys = files::storage::keys(RECORD_KEY_PREFIX);
+            keys.sort_unstable_by(|a, b| b.cmp(a));
+            for key in keys {
+                if ui.button(&key[RECORD_KEY_PREFIX.len()..]).clicked()
+                    && let Some(content) = files::storage::get(&key)
+                    && let Err(e) = GameRecord::parse(&content).and_then(|r| self.load_record(r))
+                {
+                    self.record_error = Some(e);
+                }
+            }
+        }
+        if let Some(e) = &self.record_error {
+            ui.colored_label(Color32::RED, e);
+        }
+    }
+
+    /// How the game ended, e.g. "You win by resignation".
+    fn result_text(&self) -> Option<String> {
+        let result = result(&self.ai.lock().unwrap())?;
+        let reason = match self.termination {
+            Some(Termination::Time) => "on time",
+            Some(Termination::Resignation) => "by resignation",
+            Some(Termination::Agreement) => "by agreement",
+            None if self.win_line.is_some() => "with five in a row",
+            None => "",
+        };
+        let human = if self.role_black { "1-0" } else { "0-1" };
+        let winner = match result {
+            "1/2-1/2" => "Draw",
+            _ if self.mode == Mode::HumanVsAI && result == human => "You win",
+            _ if self.mode == Mode::HumanVsAI => "AI wins",
+            "1-0" => "Black wins",
+            _ => "White wins",
+        };
+        if reason.is_empty() {
+            return Some(tr(winner).to_string());
+        }
+        Some(trf("{0} {1}", &[&tr(winner), &tr(reason)]))
+    }
+
+    /// The result panel: how the game ended, its length and what to do next.
+    fn show_result(&mut self, ui: &mut egui::Ui) {
+        if let Some(text) = self.result_text() {
+            let heading = ui.heading(text);
+            live(ui, &heading);
+        }
+        let moves = self.move_times.len() - self.setup;
+        let duration: u64 = self.move_times.iter().sum();
+        ui.label(trf(
+            "{0} moves in {1}",
+            &[&moves, &clock::format_ms(duration)],
+        ));
+        match self.mode {
+            Mode::HumanVsAI => {
+                ui.label(trf("AI depth {0}", &[&self.depth]));
+            }
+            Mode::AIVsAI => {
+                ui.label(trf("Depths {0}:{1}", &[&self.depth, &self.white_depth]));
+            }
+            Mode::HumanVsHuman => {}
+        }
+        ui.horizontal_wrapped(|ui| {
+            if ui.button(tr("Review")).clicked() {
+                match Review::new(self.to_record()) {
+                    Ok(review) => {
+                        self.review = Some(review);
+                        self.state = AppState::Review;
+                    }
+                    Err(e) => log::error!("Can not review the game: {}", e),
+                }
+            }
+            let rematch = if self.mode == Mode::HumanVsHuman {
+                "Rematch"
+            } else {
+                "Rematch, colours swapped"
+            };
+            if ui.button(tr(rematch)).clicked() {
+                self.rematch();
+                return;
+            }
+            if ui.button(tr("New game")).clicked() {
+                self.new_game();
+                return;
+            }
+            if self.report_rx.is_none()
+                && self.report.is_empty()
+                && ui.button(tr("Analyse game")).clicked()
+            {
+                self.start_report();
+            }
+        });
+    }
+
+    /// Starts the next game at once, the AI or AIs playing the other colours.
+    fn rematch(&mut self) {
+        self.new_game();
+        match self.mode {
+            Mode::HumanVsAI => self.role_black = !self.role_black,
+            Mode::AIVsAI => {
+                std::mem::swap(&mut self.depth, &mut self.white_depth);
+                std::mem::swap(&mut self.profile, &mut self.white_profile);
+            }
+            Mode::HumanVsHuman => {}
+        }
+        self.start_game();
+    }
+
+    /// The panel beside the board: clocks, the engine's outlook and the hint, then the
+    /// controls of the current state.
+    fn show_side(&mut self, ui: &mut egui::Ui, fps: f64) {
+        // The result panel announces the end of the game.
+        if self.state != AppState::Settlement {
+            let status = ui.label(self.status_text());
+            live(ui, &status);
+        }
+        if let Some(clocks) = self.clocks
+            && self.state != AppState::Review
+        {
+            let running = self.clock_running();
+            let black = self.black_to_move();
+            let elapsed = self.move_clock.elapsed().as_millis() as u64;
+            for (i, side) in ["Black", "White"].into_iter().enumerate() {
+                let on_move = running && black == (i == 0);
+                let face = clocks[i].show(if on_move { elapsed } else { 0 });
+                let text = egui::RichText::new(format!("{} {}", tr(side), face))
+                    .monospace()
+                    .size(18.0);
+                ui.label(if on_move { text.strong() } else { text.weak() });
+            }
+            ui.separator();
+        }
+        if let Some(outlook) = self.outlook
+            && self.state == AppState::Gaming
+        {
+            let side = match self.mode {
+                Mode::AIVsAI if self.black_to_move() => "White",
+                Mode::AIVsAI => "Black",
+                _ => "AI",
+            };
+            let text = match outlook {
+                Outlook::WinIn(n) => trf("{0}: win in {1}", &[&tr(side), &n]),
+                Outlook::LossIn(n) => trf("{0}: loss in {1}", &[&tr(side), &n]),
+            };
+            ui.colored_label(Color32::RED, text);
+        }
+        if self.draw_declined == Some(self.move_times.len()) && self.state == AppState::Gaming {
+            ui.label(tr("The AI declines the draw."));
+        }
+        if self.draw_rx.is_some() {
+            ui.label(tr("The AI considers the draw offer..."));
+        }
+        if let Some(hint) = &self.hint
+            && self.state == AppState::Gaming
+        {
+            let reply = hint
+                .reply
+                .map(|reply| trf(", expected reply {0}", &[&self.notation.display(reply)]))
+                .unwrap_or_default();
+            ui.colored_label(
+                self.theme.hint(),
+                trf(
+                    "Hint: {0} ({1}){2}",
+                    &[
+                        &self.notation.display(hint.step),
+                        &format_score(hint.score),
+                        &reply,
+                    ],
+                ),
+            );
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if self.mode == Mode::AIVsAI
+                && (self.state == AppState::Gaming || self.state == AppState::AIThinking)
+            {
+                ui.checkbox(&mut self.paused, tr("Pause"));
+                ui.add(
+                    egui::Slider::new(&mut self.move_delay, 0..=3000).text(tr("move delay (ms)")),
+                );
+                if self.paused
+                    && self.state == AppState::Gaming
+                    && ui.button(tr("Next move")).clicked()
+                {
+                    self.start_ai_move();
+                }
+            } else if self.state == AppState::Gaming {
+                if self.is_human(self.black_to_move()) {
+                    self.show_move_entry(ui);
+                }
+                self.show_hints(ui);
+            }
+            if self.state == AppState::Review {
+                self.show_move_entry(ui);
+                self.show_review(ui);
+            }
+            if self.state == AppState::Settlement {
+                self.show_result(ui);
+                self.show_report(ui);
+            }
+            ui.separator();
+            ui.weak(format!("FPS: {:.2}", fps));
+        });
+    }
+
+    /// Hint and analysis controls, with the analysed moves and the line of the one under
+    /// the pointer.
+    fn show_hints(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(self.can_hint(), egui::Button::new(self.hint_label()))
+            .clicked()
+        {
+            self.request_hint();
+        }
+        ui.checkbox(&mut self.analysis, tr("Analysis"));
+        if !self.analysis {
+            return;
+        }
+        if let Some(best) = self.candidates.first()
+            && ui.button(tr("Save line")).clicked()
+        {
+            let ply = self.ai.lock().unwrap().all_steps.len() - self.setup;
+            let mut moves: Vec<RecordMove> = best
+                .pv
+                .iter()
+                .map(|&step| RecordMove {
+                    step: to_notation(step),
+                    time_ms: 0,
+                    comment: None,
+                })
+                .collect();
+            if let Some(first) = moves.first_mut() {
+                first.comment = Some(format!("Analysis: {}", format_score(best.score)));
+            }
+            self.variations.push(Variation {
+                ply,
+                moves,
+                variations: Vec::new(),
+            });
+        }
+        for (rank, candidate) in self.candidates.iter().take(self.analysis_top).enumerate() {
+            ui.label(format!(
+                "{}. {}: {}",
+                rank + 1,
+                self.notation.display(candidate.step),
+                format_score(candidate.score)
+            ));
+        }
+        let hovered = self.layout.and_then(|layout| {
+            let cell = layout.cell_at(ui.ctx().pointer_hover_pos()?)?;
+            self.candidates.iter().find(|c| c.step == cell)
+        });
+        if let Some(candidate) = hovered {
+            let line: Vec<String> = candidate
+                .pv
+                .iter()
+                .map(|&step| self.notation.display(step))
+                .collect();
+            ui.label(format!(
+                "{}: {}",
+                format_score(candidate.score),
+                line.join(" ")
+            ));
+        }
+    }
+
+    /// Plays `cell` for the human to move, or only marks it when moves need confirming, or
+    /// tries it as a variation in review. Occupied cells are ignored.
+    fn play_at(&mut self, (x, y): (usize, usize)) {
+        if self.stone_at((x, y)) != 0 {
+            return;
+        }
+        if self.state == AppState::Gaming && self.is_human(self.black_to_move()) {
+            if self.confirm_moves && self.pending != Some((x, y)) {
+                self.pending = Some((x, y));
+                return;
+            }
+            self.pending = None;
+            let black = self.black_to_move();
+            self.record_step((x, y), black);
+            self.hint = None;
+            self.hint_rx = None;
+            self.draw_rx = None;
+            self.stop_analysis();
+            self.place((x, y), black);
+            self.next_turn();
+        } else if self.state == AppState::Review
+            && let Some(review) = &mut self.review
+        {
+            review.play((x, y));
+        }
+    }
+
+    /// The stone on `cell` of the board shown: 0 for none, 1 for black, 2 for white.
+    fn stone_at(&self, (x, y): (usize, usize)) -> i32 {
+        match &self.review {
+            Some(review) => review.board()[x][y].0,
+            None => self.board[x][y],
+        }
+    }
+
+    /// Moves the keyboard cursor with the arrow keys, from the last move or the centre,
+    /// while no other widget has the keyboard. The board then takes the focus, so that
+    /// Enter and Space click it. In review the keys step through the game instead.
+    fn handle_keys(&mut self, ctx: &egui::Context, board: egui::Id) {
+        let focused = ctx.memory(|m| m.focused());
+        if focused.is_some_and(|id| id != board) {
+            return;
+        }
+        // Arrows move the cursor, or step through the review, rather than the focus.
+        if focused == Some(board) {
+            let filter = egui::EventFilter {
+                horizontal_arrows: true,
+                vertical_arrows: true,
+                ..Default::default()
+            };
+            ctx.memory_mut(|m| m.set_focus_lock_filter(board, filter));
+        }
+        if self.state == AppState::Review {
+            self.review_keys(ctx);
+            return;
+        }
+        let arrows = [
+            (egui::Key::ArrowUp, -1, 0),
+            (egui::Key::ArrowDown, 1, 0),
+            (egui::Key::ArrowLeft, 0, -1),
+            (egui::Key::ArrowRight, 0, 1),
+        ];
+        let (mut x, mut y) = self
+            .cursor
+            .or(self.last_step)
+            .unwrap_or((BOARD_SIZE / 2, BOARD_SIZE / 2));
+        let mut moved = false;
+        for (key, dx, dy) in arrows {
+            if ctx.input(|i| i.key_pressed(key)) {
+                x = x.saturating_add_signed(dx).min(BOARD_SIZE - 1);
+                y = y.saturating_add_signed(dy).min(BOARD_SIZE - 1);
+                moved = true;
+            }
+        }
+        if moved {
+            self.cursor = Some((x, y));
+            ctx.memory_mut(|m| m.request_focus(board));
+        }
+    }
+
+    /// Left and right step through the review, Home and End jump to its ends.
+    fn review_keys(&mut self, ctx: &egui::Context) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
+        let last = review.last_ply();
+        ctx.input(|i| {
+            if i.key_pressed(egui::Key::ArrowLeft) {
+                review.go_to(review.ply.saturating_sub(1));
+            }
+            if i.key_pressed(egui::Key::ArrowRight) {
+                review.go_to(review.ply + 1);
+            }
+            if i.key_pressed(egui::Key::Home) {
+                review.go_to(0);
+            }
+            if i.key_pressed(egui::Key::End) {
+                review.go_to(last);
+            }
+        });
+    }
+
+    /// What a screen reader says for the board: the cell under the keyboard cursor.
+    fn board_label(&self) -> String {
+        let Some(cell) = self.cursor else {
+            return tr("Board").to_string();
+        };
+        let stone = match self.stone_at(cell) {
+            0 => tr("empty"),
+            1 => tr("Black"),
+            _ => tr("White"),
+        };
+        trf("Board, {0}: {1}", &[&self.notation.display(cell), &stone])
+    }
+
+    /// The last move and what happens next, read out by screen readers as it changes.
+    fn status_text(&self) -> String {
+        let next = match self.state {
+            AppState::AIThinking => tr("AI Thinking").to_string(),
+            AppState::Review => tr("Review").to_string(),
+            _ if self.mode == Mode::HumanVsAI && self.is_human(self.black_to_move()) => {
+                tr("Your move").to_string()
+            }
+            _ if self.black_to_move() => tr("Black to move").to_string(),
+            _ => tr("White to move").to_string(),
+        };
+        match self.last_step {
+            Some(step) if self.state != AppState::Review => {
+                let side = if self.board[step.0][step.1] == 1 {
+                    tr("Black")
+                } else {
+                    tr("White")
+                };
+                trf(
+                    "{0} played {1}. {2}",
+                    &[&side, &self.notation.display(step), &next],
+                )
+            }
+            _ => next,
+        }
+    }
+
+    /// A field to play a move by typing its coordinates, e.g. `h8`.
+    fn show_move_entry(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Type a move"));
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.move_text)
+                    .hint_text(self.notation.display((BOARD_SIZE / 2, BOARD_SIZE / 2)))
+                    .desired_width(60.0),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                match parse_display(&self.move_text) {
+                    Some(cell) if self.stone_at(cell) == 0 => {
+                        self.move_error = None;
+                        self.move_text.clear();
+                        self.cursor = Some(cell);
+                        self.play_at(cell);
+                        response.request_focus();
+                    }
+                    Some(cell) => {
+                        self.move_error =
+                            Some(trf("{0} is taken", &[&self.notation.display(cell)]));
+                    }
+                    None => {
+                        self.move_error = Some(trf("Not a move: {0}", &[&self.move_text]));
+                    }
+                }
+            }
+        });
+        if let Some(e) = &self.move_error {
+            ui.colored_label(Color32::RED, e);
+        }
+    }
+
+    /// The board with the stones and overlays; clicks, or Enter on the keyboard cursor,
+    /// play moves or try variations.
+    fn show_board(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click());
+        let layout = BoardLayout::fit(rect);
+        self.layout = Some(layout);
+        let grid = layout.grid;
+        let painter = ui.painter();
+        let theme = &self.theme;
+        painter.rect_filled(rect, 0.0, theme.board());
+        let stroke = egui::Stroke::new(1.0, theme.lines());
+        let last_line = BOARD_SIZE as f32;
+        for i in 1..=BOARD_SIZE {
+            let i = i as f32;
+            painter.line_segment([layout.at(i, 1.0), layout.at(i, last_line)], stroke);
+            painter.line_segment([layout.at(1.0, i), layout.at(last_line, i)], stroke);
+        }
+        // Tengen and the four hoshi, on the fourth lines from the edges.
+        let (centre, near, far) = (BOARD_SIZE / 2, 3, BOARD_SIZE - 4);
+        for cell in [
+            (centre, centre),
+            (near, near),
+            (near, far),
+            (far, near),
+            (far, far),
+        ] {
+            painter.circle_filled(layout.center(cell), grid / 10.0, theme.lines());
+        }
+        let label_font = FontId::proportional(grid * 0.3);
+        for i in 0..BOARD_SIZE {
+            let line = (i + 1) as f32;
+            painter.text(
+                layout.at(line, 0.65),
+                Align2::CENTER_CENTER,
+                self.notation.column_label(i),
+                label_font.clone(),
+                theme.lines(),
+            );
+            painter.text(
+                layout.at(0.6, line),
+                Align2::CENTER_CENTER,
+                self.notation.row_label(i),
+                label_font.clone(),
+                theme.lines(),
+            );
+        }
+        self.handle_keys(ui.ctx(), response.id);
+        if response.clicked_by(egui::PointerButton::Primary) {
+            self.cursor = None;
+            response.request_focus();
+            if let Some(cell) = response
+                .interact_pointer_pos()
+                .and_then(|pos| layout.cell_at(pos))
+            {
+                self.play_at(cell);
+            }
+        } else if response.clicked()
+            && let Some(cell) = self.cursor
+        {
+            // Enter or Space on the focused board, or a screen reader's click.
+            self.play_at(cell);
+        }
+        response.widget_info(|| {
+            egui::WidgetInfo::labeled(egui::WidgetType::Other, true, self.board_label())
+        });
+        let review_board = self.review.as_ref().map(Review::board);
+        let last_step = self
+            .review
+            .as_ref()
+            .map_or(self.last_step, Review::last_step);
+        let radius = layout.stone_radius();
+        for x in 0..BOARD_SIZE {
+            for y in 0..BOARD_SIZE {
+                let center = layout.center((x, y));
+                let (stone, number) = review_board.map_or((self.board[x][y], 0), |b| b[x][y]);
+                if stone != 0 {
+                    self.theme.paint_stone(painter, center, radius, stone == 1);
+                }
+                if number > 0 {
+                    painter.text(
+                        center,
+                        Align2::CENTER_CENTER,
+                        number.to_string(),
+                        FontId::proportional(grid / 2.5),
+                        self.theme.on_stone(stone == 1),
+                    );
+                }
+                if last_step == Some((x, y)) {
+                    self.theme.paint_marker(painter, center, radius);
+                }
+            }
+        }
+        if self.state == AppState::Gaming && self.is_human(self.black_to_move()) {
+            self.paint_preview(painter, response.hover_pos(), &layout);
+        }
+        if let Some(cell) = self.cursor {
+            let half = egui::Vec2::splat(grid / 2.0);
+            let center = layout.center(cell);
+            painter.rect_stroke(
+                egui::Rect::from_min_max(center - half, center + half),
+                grid / 8.0,
+                egui::Stroke::new(2.5, self.theme.highlight()),
+                egui::StrokeKind::Inside,
+            );
+        }
+        let banner = FontId::proportional((grid * 0.8).min(32.0));
+        if self.state == AppState::AIThinking && self.mode == Mode::HumanVsAI {
+            painter.rect_filled(
+                layout.rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(0, 0, 0, 50),
+            );
+            painter.text(
+                layout.rect.center(),
+                Align2::CENTER_CENTER,
+                tr("AI Thinking"),
+                banner,
+                Color32::RED,
+            );
+        } else if self.state == AppState::Settlement {
+            let t = self.ended_at.map_or(1.0, |at| {
+                (at.elapsed().as_secs_f32() / SETTLE_ANIMATION).min(1.0)
+            });
+            let color = self.theme.highlight();
+            if let Some(line) = self.win_line {
+                let cells = line.cells();
+                let (from, to) = (layout.center(cells[0]), layout.center(cells[4]));
+                painter.line_segment(
+                    [from, from + (to - from) * t],
+                    egui::Stroke::new(radius / 3.0, color.gamma_multiply(0.8)),
+                );
+                for cell in cells {
+                    painter.circle_stroke(
+                        layout.center(cell),
+                        radius,
+                        egui::Stroke::new(3.0, color.gamma_multiply(t)),
+                    );
+                }
+            }
+            // A ripple spreading from the final move.
+            if let Some(step) = self.last_step
+                && t < 1.0
+            {
+                painter.circle_stroke(
+                    layout.center(step),
+                    radius * (1.0 + 1.5 * t),
+                    egui::Stroke::new(2.0, color.gamma_multiply(1.0 - t)),
+                );
+            }
+        }
+        if self.state == AppState::Gaming {
+            self.paint_analysis(painter, response.hover_pos(), &layout);
+        }
+        if let Some(hint) = &self.hint
+            && self.state == AppState::Gaming
+        {
+            for (rank, &step) in hint.alternatives.iter().enumerate() {
+                painter.circle_stroke(
+                    layout.center(step),
+                    radius,
+                    egui::Stroke::new(2.0, self.theme.hint()),
+                );
+                painter.text(
+                    layout.center(step),
+                    Align2::CENTER_CENTER,
+                    (rank + 2).to_string(),
+                    FontId::proportional(grid / 2.0),
+                    self.theme.hint(),
+                );
+            }
+            painter.circle_filled(
+                layout.center(hint.step),
+                radius,
+                self.theme.hint().gamma_multiply(0.47),
+            );
+            if let Some(reply) = hint.reply {
+                painter.circle_stroke(
+                    layout.center(reply),
+                    grid / 4.0,
+                    egui::Stroke::new(2.0, self.theme.reply()),
+                );
+            }
+        }
+    }
+
+    /// Theme choice, stone shading, the last-move marker and safe colours, and loading theme
+    /// files.
+    fn show_theme_settings(&mut self, ui: &mut egui::Ui) {
+        if let Some(rx) = &self.theme_rx
+            && let Ok((name, content)) = rx.try_recv()
+        {
+            self.theme_rx = None;
+            match Theme::parse(&name, &String::from_utf8_lossy(&content)) {
+                Ok(theme) => {
+                    self.add_theme(theme.clone());
+                    self.theme = theme;
+                    self.theme_error = None;
+                }
+                Err(e) => self.theme_error = Some(format!("{}: {}", name, e)),
+            }
+        }
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label(tr("theme"))
+                .selected_text(&self.theme.name)
+                .show_ui(ui, |ui| {
+                    for theme in &self.themes {
+                        if ui
+                            .selectable_label(theme.name == self.theme.name, &theme.name)
+                            .clicked()
+                        {
+                            self.theme = theme.clone();
+                        }
+                    }
+                });
+            if ui.button(tr("Load theme...")).clicked() {
+                let (tx, rx) = mpsc::channel();
+                self.theme_rx = Some(rx);
+                files::pick_file(tx, &["json", "toml"]);
+            }
+        });
+        if let Some(e) = &self.theme_error {
+            ui.colored_label(Color32::RED, e);
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.theme.shaded, tr("Shaded stones"));
+            egui::ComboBox::from_label(tr("last move marker"))
+                .selected_text(tr(self.theme.marker.name()))
+                .show_ui(ui, |ui| {
+                    for marker in Marker::ALL {
+                        ui.selectable_value(&mut self.theme.marker, marker, tr(marker.name()));
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            let color = SAFE_COLORS
+                .iter()
+                .find(|(_, rgb)| *rgb == self.theme.marker_color)
+                .map_or("Custom", |(name, _)| name);
+            egui::ComboBox::from_label(tr("marker colour"))
+                .selected_text(tr(color))
+                .show_ui(ui, |ui| {
+                    for (name, rgb) in SAFE_COLORS {
+                        ui.selectable_value(&mut self.theme.marker_color, rgb, tr(name));
+                    }
+                });
+            ui.checkbox(&mut self.theme.safe_colors, tr("Colour-blind safe colours"));
+        });
+    }
+
+    /// Paints a translucent stone of the side to move where a click would play, and the
+    /// move waiting for confirmation.
+    fn paint_preview(&self, painter: &egui::Painter, hover: Option<Pos2>, layout: &BoardLayout) {
+        let radius = layout.stone_radius();
+        let black = self.black_to_move();
+        let ring = self.theme.stone(black);
+        let fill = ring.gamma_multiply(if black { 0.35 } else { 0.55 });
+        if let Some((x, y)) = self.pending {
+            painter.circle_filled(layout.center((x, y)), radius, fill.gamma_multiply(1.8));
+            painter.circle_stroke(
+                layout.center((x, y)),
+                radius + 2.0,
+                egui::Stroke::new(2.0, ring),
+            );
+        }
+        if let Some((x, y)) = hover.and_then(|pos| layout.cell_at(pos))
+            && self.board[x][y] == 0
+            && self.pending != Some((x, y))
+        {
+            painter.circle_filled(layout.center((x, y)), radius, fill);
+        }
+    }
+
+    /// Paints the analysis heatmap, the numbered top moves, the evaluation bar and the
+    /// principal variation of the hovered candidate.
+    fn paint_analysis(&self, painter: &egui::Painter, hover: Option<Pos2>, layout: &BoardLayout) {
+        let radius = layout.stone_radius();
+        let font = FontId::proportional(layout.grid / 2.0);
+        for (rank, candidate) in self.candidates.iter().enumerate() {
+            let p = win_probability(candidate.score);
+            painter.circle_filled(layout.center(candidate.step), radius, self.theme.outcome(p));
+            if rank < self.analysis_top {
+                painter.text(
+                    layout.center(candidate.step),
+                    Align2::CENTER_CENTER,
+                    (rank + 1).to_string(),
+                    font.clone(),
+                    Color32::BLACK,
+                );
+            }
+        }
+        let black_to_move = self.black_to_move();
+        if let Some(best) = self.candidates.first() {
+            let p = win_probability(best.score);
+            let black = if black_to_move { p } else { 1.0 - p };
+            // In the left margin, from the first line to the last.
+            let (left, right) = (0.125, 0.375);
+            let (top, bottom) = (1.0, BOARD_SIZE as f32);
+            let split = bottom - (bottom - top) * black;
+            painter.rect_filled(
+                egui::Rect::from_min_max(layout.at(left, top), layout.at(right, split)),
+                0.0,
+                Color32::WHITE,
+            );
+            painter.rect_filled(
+                egui::Rect::from_min_max(layout.at(left, split), layout.at(right, bottom)),
+                0.0,
+                Color32::BLACK,
+            );
+        }
+        let hovered = hover
+            .and_then(|pos| layout.cell_at(pos))
+            .and_then(|cell| self.candidates.iter().find(|c| c.step == cell));
+        if let Some(candidate) = hovered {
+            for (i, &step) in candidate.pv.iter().enumerate() {
+                let black = black_to_move == (i % 2 == 0);
+                let (fill, text) = if black {
+                    (Color32::from_black_alpha(140), Color32::WHITE)
+                } else {
+                    (Color32::from_white_alpha(180), Color32::BLACK)
+                };
+                painter.circle_filled(layout.center(step), radius, fill);
+                painter.text(
+                    layout.center(step),
+                    Align2::CENTER_CENTER,
+                    (i + 1).to_string(),
+                    font.clone(),
+                    text,
+                );
+            }
+        }
+    }
+}
+
+/// Lets the web page paint between the steps of a long task, since tasks there run on
+/// the page's only thread. Native tasks run on workers and go straight on.
+async fn yield_to_page() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use std::task::Poll;
+        use wasm_bindgen::JsCast;
+
+        let mut scheduled = false;
+        std::future::poll_fn(|cx| {
+            if scheduled {
+                return Poll::Ready(());
+            }
+            scheduled = true;
+            // A timer rather than an immediate wake, which would resume before painting.
+            let waker = cx.waker().clone();
+            let wake = wasm_bindgen::closure::Closure::once_into_js(move || waker.wake());
+            let timer = web_sys::window()
+                .map(|window| window.set_timeout_with_callback(wake.unchecked_ref()));
+            if matches!(timer, Some(Ok(_))) {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+    }
+}
+
+/// A framed group of the start menu with a title.
+fn section(ui: &mut egui::Ui, title: &str, add_contents: impl FnOnce(&mut egui::Ui)) {
+    ui.group(|ui| {
+        ui.set_width(300.0);
+        ui.vertical(|ui| {
+            ui.strong(title);
+            add_contents(ui);
+        });
+    });
+}
+
+/// The result of the game from black's point of view, if it is over.
+fn result(ai: &AI) -> Option<&'static str> {
+    match ai.state {
+        GameState::Human if ai.ai_black => Some("0-1"),
+        GameState::AI if ai.ai_black => Some("1-0"),
+        GameState::Human => Some("1-0"),
+        GameState::AI => Some("0-1"),
+        GameState::Draw => Some("1/2-1/2"),
+        GameState::Idle => None,
+    }
+}
+
+/// Makes screen readers read `response` out whenever its text changes.
+fn live(ui: &egui::Ui, response: &egui::Response) {
+    ui.ctx().accesskit_node_builder(response.id, |node| {
+        node.set_live(egui::accesskit::Live::Polite);
+    });
+}
+
+fn time_control_select(ui: &mut egui::Ui, time_control: &mut Option<TimeControl>) {
+    let name = time_control.as_ref().map_or("Untimed", TimeControl::name);
+    egui::ComboBox::from_label(tr("time control"))
+        .selected_text(tr(name))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(time_control, None, tr("Untimed"));
+            for preset in TimeControl::PRESETS {
+                let selected = time_control.is_some_and(|c| c.name() == preset.name());
+                if ui.selectable_label(selected, tr(preset.name())).clicked() && !selected {
+                    *time_control = Some(preset);
+                }
+            }
+        });
+    // Sliders in whole minutes or seconds over a value in milliseconds.
+    let slider = |ui: &mut egui::Ui, ms: &mut u64, unit_ms: u64, max: u64, text: &str| {
+        let mut units = *ms / unit_ms;
+        ui.add(egui::Slider::new(&mut units, 1..=max).text(tr(text)));
+        *ms = units * unit_ms;
+    };
+    match time_control {
+        Some(TimeControl::SuddenDeath { main_ms }) => {
+            slider(ui, main_ms, 60_000, 60, "minutes");
+        }
+        Some(TimeControl::Fischer {
+            main_ms,
+            increment_ms,
+        }) => {
+            slider(ui, main_ms, 60_000, 60, "minutes");
+            slider(ui, increment_ms, 1000, 60, "increment (s)");
+        }
+        Some(TimeControl::ByoYomi {
+            main_ms,
+            period_ms,
+            periods,
+        }) => {
+            slider(ui, main_ms, 60_000, 60, "minutes");
+            slider(ui, period_ms, 1000, 120, "period (s)");
+            ui.add(egui::Slider::new(periods, 1..=10).text(tr("periods")));
+        }
+        None => {}
+    }
+}
+
+fn profile_select(ui: &mut egui::Ui, label: &str, profile: &mut Profile, custom: bool) {
+    egui::ComboBox::from_label(label)
+        .selected_text(tr(profile.name()))
+        .show_ui(ui, |ui| {
+            for builtin in Profile::BUILTIN {
+                ui.selectable_value(profile, builtin, tr(builtin.name()));
+            }
+            if custom {
+                ui.selectable_value(profile, Profile::Custom, tr(Profile::Custom.name()));
+            }
+        });
+}
+
+fn format_score(score: i32) -> String {
+    match Outlook::from_score(score) {
+        Some(Outlook::WinIn(n)) => trf("win in {0}", &[&n]),
+        Some(Outlook::LossIn(n)) => trf("loss in {0}", &[&n]),
+        None => score.to_string(),
+    }
+}
+impl eframe::App for GobangApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // JSON rather than eframe's RON, which can not read the tagged time controls back.
+        match serde_json::to_string(&self.settings()) {
+            Ok(json) => storage.set_string(SETTINGS_KEY, json),
+            Err(e) => log::error!("Can not save the settings: {}", e),
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let frame_time = ctx.input(|i| i.time);
+        let fps = if frame_time - self.last_frame_time > 0.0 {
+            1.0 / (frame_time - self.last_frame_time)
+        } else {
+            0.0
+        };
+        self.last_frame_time = frame_time;
+        self.poll_record();
+        if self.state == AppState::Idle {
+            egui::CentralPanel::default().show(ctx, |ui| self.show_start_menu(ui, fps));
+            return;
+        }
+        #[cfg(target_arch = "wasm32")]
+        for task in self.lazy_tasks.drain(..) {
+            spawn_local(task);
+        }
+        self.poll();
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| self.show_toolbar(ui));
+        // The panel goes beside the board on wide screens and under it on narrow ones.
+        let screen = ctx.screen_rect();
+        if screen.width() < screen.height() + SIDE_PANEL_WIDTH {
+            egui::TopBottomPanel::bottom("side")
+                .exact_height(screen.height() / 3.0)
+                .show(ctx, |ui| self.show_side(ui, fps));
+        } else {
+            egui::SidePanel::right("side")
+                .default_width(SIDE_PANEL_WIDTH)
+                .show(ctx, |ui| self.show_side(ui, fps));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| self.show_board(ui));
+        let mut open = self.settings_open;
+        egui::Window::new(tr("Settings"))
+            .id(egui::Id::new("settings"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                self.show_assist_settings(ui);
+                ui.separator();
+                self.show_display_settings(ui);
+            });
+        self.settings_open &= open;
+        if let Some(confirm) = self.confirm {
+            self.show_confirm(ctx, confirm);
+        }
+        let animating = self
+            .ended_at
+            .is_some_and(|at| at.elapsed().as_secs_f32() < SETTLE_ANIMATION);
+        if animating {
+            ctx.request_repaint();
+        }
+        // Keep polling the channels while a background task may still deliver a result.
+        if self.state == AppState::AIThinking
+            || (self.mode == Mode::AIVsAI && self.state == AppState::Gaming && !self.paused)
+            || self.clock_running()
+            || self.hint_rx.is_some()
+            || self.draw_rx.is_some()
+            || self.analysis_rx.is_some()
+            || self.report_rx.is_some()
+        {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+    }
 }
@@ -0,0 +1,126 @@
+//! Translations of the user interface. English strings are the keys: `locales/<code>.toml`
+//! maps each of them to its translation, and anything missing stays in English.
+//! Placeholders `{0}`, `{1}`, ... stand for the arguments of `trf`, so translations may
+//! reorder them.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        OnceLock,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    Chinese,
+    Japanese,
+    Vietnamese,
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Translation tables, in the order of `Language::ALL`.
+static TABLES: OnceLock<Vec<HashMap<String, String>>> = OnceLock::new();
+
+const SOURCES: [&str; 4] = [
+    "",
+    include_str!("../locales/zh.toml"),
+    include_str!("../locales/ja.toml"),
+    include_str!("../locales/vi.toml"),
+];
+
+impl Language {
+    pub const ALL: [Language; 4] = [
+        Language::English,
+        Language::Chinese,
+        Language::Japanese,
+        Language::Vietnamese,
+    ];
+
+    /// The language's own name for itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Chinese => "中文",
+            Language::Japanese => "日本語",
+            Language::Vietnamese => "Tiếng Việt",
+        }
+    }
+
+    /// Matches a locale such as `zh-CN`, `ja_JP.UTF-8` or `vi` by its language code.
+    pub fn from_locale(locale: &str) -> Option<Language> {
+        let code = locale.split(['-', '_', '.']).next()?.to_ascii_lowercase();
+        match code.as_str() {
+            "en" => Some(Language::English),
+            "zh" => Some(Language::Chinese),
+            "ja" => Some(Language::Japanese),
+            "vi" => Some(Language::Vietnamese),
+            _ => None,
+        }
+    }
+
+    /// The first supported language the browser or the environment asks for, English if
+    /// there is none.
+    pub fn detect() -> Language {
+        #[cfg(target_arch = "wasm32")]
+        let locales: Vec<String> = web_sys::window()
+            .map(|window| {
+                window
+                    .navigator()
+                    .languages()
+                    .iter()
+                    .filter_map(|language| language.as_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        #[cfg(not(target_arch = "wasm32"))]
+        let locales: Vec<String> = ["LC_ALL", "LC_MESSAGES", "LANG", "LANGUAGE"]
+            .into_iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .flat_map(|value| value.split(':').map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        locales
+            .iter()
+            .find_map(|locale| Language::from_locale(locale))
+            .unwrap_or(Language::English)
+    }
+
+    pub fn current() -> Language {
+        Language::ALL[CURRENT.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn set(self) {
+        CURRENT.store(self as u8, Ordering::Relaxed);
+    }
+}
+
+/// `key` in the current language.
+pub fn tr(key: &str) -> &str {
+    let tables = TABLES.get_or_init(|| {
+        SOURCES
+            .iter()
+            .map(|source| {
+                toml::from_str(source).unwrap_or_else(|e| {
+                    log::error!("Malformed translation file: {}", e);
+                    HashMap::new()
+                })
+            })
+            .collect()
+    });
+    tables[Language::current() as usize]
+        .get(key)
+        .map_or(key, String::as_str)
+}
+
+/// `key` in the current language with `{0}`, `{1}`, ... replaced by `args`.
+pub fn trf(key: &str, args: &[&dyn Display]) -> String {
+    let mut text = tr(key).to_string();
+    for (i, arg) in args.iter().enumerate() {
+        text = text.replace(&format!("{{{}}}", i), &arg.to_string());
+    }
+    text
+}
@@ -0,0 +1,155 @@
+//! Board geometry, computed every frame from the space the board is given and shared by
+//! painting and hit-testing.
+
+use eframe::egui::{Pos2, Rect, Vec2};
+
+use crate::gui::BOARD_SIZE;
+
+/// How far from an intersection, in grid units, a click still reaches it.
+const SNAP_RADIUS: f32 = 0.6;
+
+/// A square board with one grid unit of margin around the lines for the edge labels.
+#[derive(Debug, Clone, Copy)]
+pub struct BoardLayout {
+    pub rect: Rect,
+    /// Distance between neighbouring lines.
+    pub grid: f32,
+}
+
+impl BoardLayout {
+    /// The largest board that fits in `available`, centred in it.
+    pub fn fit(available: Rect) -> Self {
+        let side = available.width().min(available.height()).max(0.0);
+        BoardLayout {
+            rect: Rect::from_center_size(available.center(), Vec2::splat(side)),
+            grid: side / (BOARD_SIZE + 1) as f32,
+        }
+    }
+
+    /// The point `column` and `row` grid units from the top-left corner of the board;
+    /// the lines run from 1 to `BOARD_SIZE`.
+    pub fn at(&self, column: f32, row: f32) -> Pos2 {
+        self.rect.min + Vec2::new(column, row) * self.grid
+    }
+
+    /// Centre of the intersection `(x, y)`, `x` being the row from the top.
+    pub fn center(&self, (x, y): (usize, usize)) -> Pos2 {
+        self.at((y + 1) as f32, (x + 1) as f32)
+    }
+
+    pub fn stone_radius(&self) -> f32 {
+        self.grid / 3.0
+    }
+
+    /// Nearest intersection to `pos`, if `pos` lies within `SNAP_RADIUS` grid units of it.
+    /// Points about equally far from several intersections map to none of them.
+    pub fn cell_at(&self, pos: Pos2) -> Option<(usize, usize)> {
+        if self.grid <= 0.0 {
+            return None;
+        }
+        let offset = (pos - self.rect.min) / self.grid;
+        let (column, row) = (offset.x.round(), offset.y.round());
+        let on_board = |line: f32| (1.0..=BOARD_SIZE as f32).contains(&line);
+        if !on_board(column) || !on_board(row) {
+            return None;
+        }
+        let distance = (offset - Vec2::new(column, row)).length();
+        (distance <= SNAP_RADIUS).then(|| (row as usize - 1, column as usize - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> BoardLayout {
+        // 40 pixels per grid unit, off the origin and in a wider than high area.
+        BoardLayout::fit(Rect::from_min_size(
+            Pos2::new(100.0, 20.0),
+            Vec2::new(1000.0, 640.0),
+        ))
+    }
+
+    #[test]
+    fn fits_a_centred_square() {
+        let layout = layout();
+        assert_eq!(layout.grid, 40.0);
+        assert_eq!(layout.rect.min, Pos2::new(280.0, 20.0));
+        assert_eq!(layout.center((0, 0)), Pos2::new(320.0, 60.0));
+        assert_eq!(layout.center((7, 7)), layout.rect.center());
+    }
+
+    #[test]
+    fn maps_intersections_back_to_cells() {
+        let layout = layout();
+        for x in 0..BOARD_SIZE {
+            for y in 0..BOARD_SIZE {
+                assert_eq!(layout.cell_at(layout.center((x, y))), Some((x, y)));
+            }
+        }
+    }
+
+    #[test]
+    fn snaps_within_tolerance() {
+        let layout = layout();
+        let center = layout.center((3, 5));
+        for offset in [
+            Vec2::new(15.0, 0.0),
+            Vec2::new(-15.0, 0.0),
+            Vec2::new(0.0, 19.0),
+            Vec2::new(0.0, -19.0),
+            Vec2::new(12.0, -12.0),
+        ] {
+            assert_eq!(
+                layout.cell_at(center + offset),
+                Some((3, 5)),
+                "{:?}",
+                offset
+            );
+        }
+        // Rows change with the vertical offset, columns with the horizontal one.
+        assert_eq!(layout.cell_at(center + Vec2::new(0.0, 21.0)), Some((4, 5)));
+        assert_eq!(layout.cell_at(center + Vec2::new(21.0, 0.0)), Some((3, 6)));
+    }
+
+    #[test]
+    fn ignores_ambiguous_points() {
+        let layout = layout();
+        let between = layout.center((3, 5)) + Vec2::splat(20.0);
+        assert_eq!(layout.cell_at(between), None);
+        assert_eq!(layout.cell_at(between - Vec2::splat(1.0)), None);
+    }
+
+    #[test]
+    fn edges_and_outside() {
+        let layout = layout();
+        let last = BOARD_SIZE - 1;
+        assert_eq!(
+            layout.cell_at(layout.center((0, 0)) - Vec2::splat(10.0)),
+            Some((0, 0))
+        );
+        assert_eq!(
+            layout.cell_at(layout.center((last, last)) + Vec2::splat(10.0)),
+            Some((last, last))
+        );
+        assert_eq!(
+            layout.cell_at(layout.center((0, 4)) - Vec2::new(0.0, 30.0)),
+            None
+        );
+        assert_eq!(
+            layout.cell_at(layout.center((4, last)) + Vec2::new(30.0, 0.0)),
+            None
+        );
+        assert_eq!(layout.cell_at(layout.rect.min), None);
+        assert_eq!(layout.cell_at(layout.rect.max), None);
+        assert_eq!(layout.cell_at(Pos2::new(-500.0, -500.0)), None);
+        assert_eq!(layout.cell_at(Pos2::new(1e9, 1e9)), None);
+    }
+
+    #[test]
+    fn empty_area_has_no_cells() {
+        let layout = BoardLayout::fit(Rect::from_min_size(Pos2::ZERO, Vec2::new(0.0, 300.0)));
+        assert_eq!(layout.cell_at(Pos2::ZERO), None);
+        assert_eq!(layout.cell_at(Pos2::new(0.0, 150.0)), None);
+    }
+}
@@ -1,29 +1,52 @@
 use eframe::*;
-use lazy_static::lazy_static;
-use std::sync::Mutex;
 
 mod ai;
+mod clock;
+mod files;
+mod fonts;
 mod gui;
-
-lazy_static! {
-    static ref BOARD_E_SIZE: Mutex<f32> = Mutex::new(640.0);
-    static ref GRID_SIZE: Mutex<usize> = Mutex::new(40);
-}
+mod i18n;
+mod layout;
+mod notation;
+#[cfg(not(target_arch = "wasm32"))]
+mod protocol;
+mod psq;
+mod record;
+mod report;
+mod review;
+mod sgf;
+mod theme;
+#[cfg(not(target_arch = "wasm32"))]
+mod tournament;
+#[cfg(not(target_arch = "wasm32"))]
+mod tune;
+mod weights;
 
 #[cfg(not(target_arch = "wasm32"))]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
-    let board_e_size = *BOARD_E_SIZE.lock().unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("tune") => return Ok(tune::run(&args[1..])?),
+        Some("match") => return Ok(tournament::run(&args[1..])?),
+        Some("protocol") => return Ok(protocol::run()?),
+        _ => {}
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default().with_inner_size([board_e_size, board_e_size]),
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([920.0, 680.0])
+            .with_min_inner_size([320.0, 320.0]),
 
         ..Default::default()
     };
     eframe::run_native(
         "gobang-rs",
         options,
-        Box::new(|_cc| Ok(Box::new(gui::GobangApp::new()))),
+        Box::new(|cc| {
+            fonts::load(&cc.egui_ctx);
+            Ok(Box::new(gui::GobangApp::restore(cc.storage)))
+        }),
     )
     .unwrap();
     Ok(())
@@ -41,13 +64,6 @@ fn main() {
     wasm_bindgen_futures::spawn_local(async move {
         let window = web_sys::window().expect("No window");
 
-        // web_sys::console::log_1(&window.inner_width().unwrap());
-
-        if window.inner_width().unwrap().as_f64().unwrap() < 640.0 {
-            *BOARD_E_SIZE.lock().unwrap() = 320.0;
-            *GRID_SIZE.lock().unwrap() = 20;
-        }
-
         let document = window.document().expect("No document");
 
         let canvas = document
@@ -60,7 +76,10 @@ fn main() {
             .start(
                 canvas,
                 web_options,
-                Box::new(|_cc| Ok(Box::new(gui::GobangApp::new()))),
+                Box::new(|cc| {
+                    fonts::load(&cc.egui_ctx);
+                    Ok(Box::new(gui::GobangApp::restore(cc.storage)))
+                }),
             )
             .await
             .unwrap();
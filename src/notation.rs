@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+use crate::gui::BOARD_SIZE;
+
+/// How cells are shown to the player. Files and logs always use standard notation so
+/// that other programs can read them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Style {
+    /// Column letters `a`-`o` and rows `1`-`15` from the bottom, e.g. `h8`.
+    Standard,
+    /// Column then row, both counted from 0 at the top-left corner, as in the Gomocup
+    /// protocol, e.g. `7,7`.
+    ZeroBased,
+}
+
+impl Style {
+    pub const ALL: [Style; 2] = [Style::Standard, Style::ZeroBased];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Style::Standard => "a-o / 1-15",
+            Style::ZeroBased => "0-based",
+        }
+    }
+
+    /// Formats a cell in this style.
+    pub fn display(self, (x, y): (usize, usize)) -> String {
+        match self {
+            Style::Standard => to_notation((x, y)),
+            Style::ZeroBased => format!("{},{}", y, x),
+        }
+    }
+
+    /// Re-formats a cell stored in standard notation in this style.
+    pub fn display_notation(self, s: &str) -> String {
+        parse_notation(s).map_or_else(|| s.to_string(), |cell| self.display(cell))
+    }
+
+    /// Edge label of column `y`.
+    pub fn column_label(self, y: usize) -> String {
+        match self {
+            Style::Standard => ((b'a' + y as u8) as char).to_string(),
+            Style::ZeroBased => y.to_string(),
+        }
+    }
+
+    /// Edge label of row `x`, counted from the top.
+    pub fn row_label(self, x: usize) -> String {
+        match self {
+            Style::Standard => (BOARD_SIZE - x).to_string(),
+            Style::ZeroBased => x.to_string(),
+        }
+    }
+}
+
+/// Formats a board cell in standard notation: column letter `a`-`o` from the left
+/// and row number `1`-`15` from the bottom, so the centre `(7, 7)` is `h8`.
+pub fn to_notation((x, y): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + y as u8) as char, BOARD_SIZE - x)
+}
+
+/// Parses a single cell written in standard notation, e.g. `h8`.
+pub fn parse_notation(s: &str) -> Option<(usize, usize)> {
+    let s = s.trim().to_ascii_lowercase();
+    let mut chars = s.chars();
+    let column = chars.next()?;
+    if !column.is_ascii_lowercase() {
+        return None;
+    }
+    let y = (column as u8 - b'a') as usize;
+    let row: usize = chars.as_str().parse().ok()?;
+    if y >= BOARD_SIZE || row == 0 || row > BOARD_SIZE {
+        return None;
+    }
+    Some((BOARD_SIZE - row, y))
+}
+
+/// Parses a cell typed by the player in either style, e.g. `h8` or `7,7`.
+pub fn parse_display(s: &str) -> Option<(usize, usize)> {
+    let Some((column, row)) = s.split_once(',') else {
+        return parse_notation(s);
+    };
+    let (x, y) = (row.trim().parse().ok()?, column.trim().parse().ok()?);
+    (x < BOARD_SIZE && y < BOARD_SIZE).then_some((x, y))
+}
+
+/// Parses a move list, either separated by whitespace or commas (`h8 i9 j10`) or run
+/// together as in RenLib and Gomoku Online clipboard strings (`h8i9j10`).
+pub fn parse_moves(s: &str) -> Option<Vec<(usize, usize)>> {
+    let mut moves = Vec::new();
+    let mut rest = s.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    while !rest.is_empty() {
+        let column = rest.chars().next()?.len_utf8();
+        let end = rest[column..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |i| i + column);
+        moves.push(parse_notation(&rest[..end])?);
+        rest = rest[end..].trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+    Some(moves)
+}
+
+/// Writes moves as a RenLib-style position string, e.g. `h8i9j10`.
+pub fn to_position(moves: &[(usize, usize)]) -> String {
+    moves.iter().map(|&m| to_notation(m)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_single_cells() {
+        assert_eq!(parse_notation("h8"), Some((7, 7)));
+        assert_eq!(parse_notation(" A15 "), Some((0, 0)));
+        assert_eq!(parse_notation("o1"), Some((14, 14)));
+        for cell in ["p1", "a16", "a0", "8h", "h", ""] {
+            assert_eq!(parse_notation(cell), None, "{}", cell);
+        }
+    }
+
+    #[test]
+    fn reads_run_together_moves() {
+        assert_eq!(parse_moves("h8i9j10"), Some(vec![(7, 7), (6, 8), (5, 9)]));
+        assert_eq!(parse_moves("a1o15"), Some(vec![(14, 0), (0, 14)]));
+        assert_eq!(parse_moves(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn reads_separated_moves() {
+        let expected = Some(vec![(7, 7), (6, 8), (5, 9)]);
+        assert_eq!(parse_moves("h8 i9 j10"), expected);
+        assert_eq!(parse_moves("h8, i9,j10"), expected);
+        assert_eq!(parse_moves("  h8\ti9\nj10 ,"), expected);
+    }
+
+    #[test]
+    fn rejects_off_board_moves() {
+        for moves in ["h8p1", "h8 a16", "a0", "h8i9x", "h8 9"] {
+            assert_eq!(parse_moves(moves), None, "{}", moves);
+        }
+    }
+
+    #[test]
+    fn round_trips_positions() {
+        let moves = vec![(7, 7), (6, 8), (5, 9), (14, 0)];
+        assert_eq!(to_position(&moves), "h8i9j10a1");
+        assert_eq!(parse_moves(&to_position(&moves)), Some(moves));
+    }
+}
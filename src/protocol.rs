@@ -0,0 +1,327 @@
+//! Gomocup (Piskvork) protocol on stdin/stdout, so the engine can be driven by
+//! tournament managers and analysis GUIs.
+//!
+//! Coordinates are `x,y` with `x` the column and `y` the row, both counted from the
+//! top-left corner. `INFO rule 1` plays standard gomoku, where only exactly five wins.
+//! Besides the standard commands, `INFO max_depth <n>` caps the search depth and
+//! `YXNBEST <k>` reports the `k` best moves as `MESSAGE` lines before playing the best
+//! one.
+
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, Write},
+};
+use web_time::Duration;
+
+use crate::{
+    ai::{AI, Candidate},
+    gui::BOARD_SIZE,
+};
+
+struct Session {
+    ai: AI,
+    depth: usize,
+    time_limit: Duration,
+    exact_five: bool,
+}
+
+impl Session {
+    fn reset(&mut self) {
+        self.ai = AI::new();
+        self.configure();
+    }
+
+    fn configure(&mut self) {
+        self.ai.depth = self.depth;
+        self.ai.time_limit = Some(self.time_limit);
+        self.ai.exact_five = self.exact_five;
+    }
+
+    fn is_free(&self, (x, y): (usize, usize)) -> bool {
+        x < BOARD_SIZE && y < BOARD_SIZE && !self.ai.all_steps.contains(&(x, y))
+    }
+
+    fn opponent_step(&mut self, step: (usize, usize)) {
+        if self.ai.all_steps.is_empty() {
+            self.ai.ai_black = false;
+        }
+        self.ai.human_step(step.0, step.1);
+    }
+
+    /// Sets up the position of a `BOARD` command from its `x,y,who` lines, `who` being 1
+    /// for the engine's stones and 2 for the opponent's, replaying them in turn order.
+    fn set_board(&mut self, rows: &[String]) -> Result<(), String> {
+        self.reset();
+        let mut own = Vec::new();
+        let mut opponent = Vec::new();
+        let mut seen = HashSet::new();
+        for row in rows {
+            let (cell, who) = row
+                .trim()
+                .rsplit_once(',')
+                .ok_or_else(|| format!("malformed line {}", row))?;
+            let step = parse_cell(cell)
+                .filter(|&step| self.is_free(step) && seen.insert(step))
+                .ok_or_else(|| format!("invalid cell {}", cell))?;
+            match who.trim() {
+                "1" => own.push(step),
+                "2" => opponent.push(step),
+                _ => return Err(format!("invalid stone owner {}", who)),
+            }
+        }
+        // The side to move has played as many stones as the other, or one fewer.
+        let ai_black = own.len() == opponent.len();
+        if !ai_black && opponent.len() != own.len() + 1 {
+            return Err(format!(
+                "{} own and {} opponent stones are not in turn order",
+                own.len(),
+                opponent.len()
+            ));
+        }
+        self.ai.ai_black = ai_black;
+        for i in 0..own.len() + opponent.len() {
+            if (i % 2 == 0) == ai_black {
+                let (x, y) = own[i / 2];
+                self.ai.ai_step(x, y);
+            } else {
+                let (x, y) = opponent[i / 2];
+                self.ai.human_step(x, y);
+            }
+        }
+        Ok(())
+    }
+
+    /// Searches and plays the engine's move, the centre on an empty board, and returns it
+    /// with its candidate lines, or `None` if the board is full.
+    fn think(&mut self, k: usize) -> Option<((usize, usize), Vec<Candidate>)> {
+        if self.ai.all_steps.is_empty() {
+            self.ai.ai_black = true;
+        }
+        if k > 1 {
+            let lines = self.ai.multi_pv(k);
+            if let Some(best) = lines.first() {
+                self.ai.ai_step(best.step.0, best.step.1);
+                return Some((best.step, lines));
+            }
+        }
+        Some((self.ai.ai()?, Vec::new()))
+    }
+}
+
+/// Reads an `x,y` cell on the board.
+fn parse_cell(s: &str) -> Option<(usize, usize)> {
+    let (column, row) = s.trim().split_once(',')?;
+    let column: usize = column.trim().parse().ok()?;
+    let row: usize = row.trim().parse().ok()?;
+    (row < BOARD_SIZE && column < BOARD_SIZE).then_some((row, column))
+}
+
+fn format_cell((row, column): (usize, usize)) -> String {
+    format!("{},{}", column, row)
+}
+
+fn respond(out: &mut impl Write, text: String) -> Result<(), String> {
+    writeln!(out, "{}", text).map_err(|e| e.to_string())?;
+    out.flush().map_err(|e| e.to_string())
+}
+
+/// Lets the engine move and prints its candidate lines followed by the move itself.
+fn respond_move(out: &mut impl Write, session: &mut Session, k: usize) -> Result<(), String> {
+    let Some((step, candidates)) = session.think(k) else {
+        return respond(out, "ERROR the board is full".to_string());
+    };
+    let mut text = String::new();
+    for (rank, candidate) in candidates.iter().enumerate() {
+        let pv: Vec<String> = candidate.pv.iter().map(|&s| format_cell(s)).collect();
+        text += &format!(
+            "MESSAGE {}. {} score {} pv {}\n",
+            rank + 1,
+            format_cell(candidate.step),
+            candidate.score,
+            pv.join(" ")
+        );
+    }
+    text += &format_cell(step);
+    respond(out, text)
+}
+
+pub fn run() -> Result<(), String> {
+    let stdin = io::stdin();
+    let mut out = io::stdout();
+    let mut lines = stdin.lock().lines();
+    let mut session = Session {
+        ai: AI::new(),
+        depth: 4,
+        time_limit: Duration::from_secs(5),
+        exact_five: false,
+    };
+    session.reset();
+    while let Some(line) = lines.next() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        match command.to_ascii_uppercase().as_str() {
+            "START" => {
+                if args.trim().parse() == Ok(BOARD_SIZE) {
+                    session.reset();
+                    respond(&mut out, "OK".to_string())?;
+                } else {
+                    respond(
+                        &mut out,
+                        format!("ERROR only {0}x{0} boards are supported", BOARD_SIZE),
+                    )?;
+                }
+            }
+            "RESTART" => {
+                session.reset();
+                respond(&mut out, "OK".to_string())?;
+            }
+            "INFO" => {
+                let (key, value) = args.split_once(' ').unwrap_or((args, ""));
+                match key.to_ascii_lowercase().as_str() {
+                    "timeout_turn" => {
+                        if let Ok(ms) = value.trim().parse::<u64>() {
+                            // Keep a margin for the protocol round trip.
+                            session.time_limit = Duration::from_millis(ms * 4 / 5);
+                        }
+                    }
+                    // Bit 1 of the rule asks for exactly five; renju is not supported.
+                    "rule" => {
+                        if let Ok(rule) = value.trim().parse::<u32>() {
+                            session.exact_five = rule & 1 == 1;
+                        }
+                    }
+                    "max_depth" => {
+                        if let Ok(depth) = value.trim().parse::<usize>() {
+                            session.depth = depth.max(1);
+                        }
+                    }
+                    _ => {}
+                }
+                session.configure();
+            }
+            "BEGIN" => respond_move(&mut out, &mut session, 1)?,
+            "TURN" => match parse_cell(args).filter(|&s| session.is_free(s)) {
+                Some(step) => {
+                    session.opponent_step(step);
+                    respond_move(&mut out, &mut session, 1)?;
+                }
+                None => respond(&mut out, format!("ERROR invalid move {}", args))?,
+            },
+            "BOARD" => {
+                let mut rows = Vec::new();
+                for line in lines.by_ref() {
+                    let line = line.map_err(|e| e.to_string())?;
+                    if line.trim().eq_ignore_ascii_case("DONE") {
+                        break;
+                    }
+                    rows.push(line);
+                }
+                match session.set_board(&rows) {
+                    Ok(()) => respond_move(&mut out, &mut session, 1)?,
+                    Err(e) => {
+                        session.reset();
+                        respond(&mut out, format!("ERROR {}", e))?;
+                    }
+                }
+            }
+            "YXNBEST" => {
+                let k = args.trim().parse().unwrap_or(1);
+                respond_move(&mut out, &mut session, k)?;
+            }
+            "ABOUT" => respond(
+                &mut out,
+                format!(
+                    "name=\"gobang-rs\", version=\"{}\"",
+                    env!("CARGO_PKG_VERSION")
+                ),
+            )?,
+            "END" => break,
+            "" => {}
+            _ => respond(&mut out, format!("UNKNOWN {}", command))?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        let mut session = Session {
+            ai: AI::new(),
+            depth: 1,
+            time_limit: Duration::from_secs(5),
+            exact_five: false,
+        };
+        session.reset();
+        session
+    }
+
+    fn rows(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_cells_on_the_board() {
+        assert_eq!(parse_cell("7,3"), Some((3, 7)));
+        assert_eq!(parse_cell(" 0 , 14 "), Some((14, 0)));
+        assert_eq!(parse_cell("14,14"), Some((14, 14)));
+        for cell in ["15,0", "0,15", "-1,0", "a,b", "7", "1,2,3", ""] {
+            assert_eq!(parse_cell(cell), None, "{}", cell);
+        }
+    }
+
+    #[test]
+    fn replays_boards_in_turn_order() {
+        let mut session = session();
+        session
+            .set_board(&rows(&["7,7,2", "8,8,1", "7,8,2"]))
+            .unwrap();
+        assert!(!session.ai.ai_black);
+        assert_eq!(session.ai.all_steps, [(7, 7), (8, 8), (8, 7)]);
+
+        session.set_board(&rows(&["7,7,1", "8,8,2"])).unwrap();
+        assert!(session.ai.ai_black);
+        assert_eq!(session.ai.all_steps, [(7, 7), (8, 8)]);
+    }
+
+    #[test]
+    fn rejects_bad_boards() {
+        let mut session = session();
+        for board in [
+            &["7,7,1", "7,7,2"][..],
+            &["15,0,1"],
+            &["7,7,3"],
+            &["7,7"],
+            &["7,7,1", "8,8,1"],
+            &["7,7,2", "8,8,2"],
+        ] {
+            assert!(session.set_board(&rows(board)).is_err(), "{:?}", board);
+        }
+    }
+
+    #[test]
+    fn nbest_plays_the_first_of_distinct_empty_moves() {
+        let mut session = session();
+        session
+            .set_board(&rows(&["7,7,2", "8,7,1", "8,8,2"]))
+            .unwrap();
+        let before = session.ai.all_steps.clone();
+        let (step, candidates) = session.think(3).unwrap();
+        let steps: HashSet<_> = candidates.iter().map(|c| c.step).collect();
+        assert_eq!(steps.len(), 3);
+        assert!(steps.iter().all(|step| !before.contains(step)));
+        assert_eq!(step, candidates[0].step);
+        assert_eq!(session.ai.all_steps.last(), Some(&step));
+    }
+
+    #[test]
+    fn takes_the_centre_on_an_empty_board() {
+        let mut session = session();
+        let (step, _) = session.think(1).unwrap();
+        assert_eq!(step, (BOARD_SIZE / 2, BOARD_SIZE / 2));
+        assert!(session.ai.ai_black);
+    }
+}
@@ -0,0 +1,92 @@
+//! Piskvork `.psq` game files as written by Gomocup tournaments.
+//!
+//! A file starts with a header such as `Piskvorky 15x15, 11:11, 0`, followed by one move
+//! per line as `x,y,time`: the column and row counted from 1 at the top-left corner and
+//! the thinking time in milliseconds. Anything after the moves (engine names, `-1`) is
+//! ignored.
+
+use crate::{gui::BOARD_SIZE, notation::to_notation};
+
+/// A board cell and the milliseconds spent choosing it.
+pub type TimedMove = ((usize, usize), u64);
+
+/// Parses a `.psq` file into its moves and their thinking times.
+pub fn parse(content: &str) -> Result<Vec<TimedMove>, String> {
+    let mut lines = content.lines().map(str::trim);
+    let header = lines.next().unwrap_or_default();
+    let size = header
+        .strip_prefix("Piskvorky")
+        .ok_or("not a Piskvork game file")?
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let (width, height) = size.split_once('x').unwrap_or((size, size));
+    if width.parse() != Ok(BOARD_SIZE) || height.parse() != Ok(BOARD_SIZE) {
+        return Err(format!(
+            "only {0}x{0} boards are supported, not {1}",
+            BOARD_SIZE, size
+        ));
+    }
+    let mut moves: Vec<TimedMove> = Vec::new();
+    for line in lines {
+        let fields: Option<Vec<usize>> = line.split(',').map(|f| f.trim().parse().ok()).collect();
+        let Some(&[column, row, ref time @ ..]) = fields.as_deref() else {
+            break;
+        };
+        if !(1..=BOARD_SIZE).contains(&column) || !(1..=BOARD_SIZE).contains(&row) {
+            return Err(format!("move {},{} is off the board", column, row));
+        }
+        let step = (row - 1, column - 1);
+        if moves.iter().any(|&(s, _)| s == step) {
+            return Err(format!("{} is played twice", to_notation(step)));
+        }
+        moves.push((step, time.first().map_or(0, |&t| t as u64)));
+    }
+    Ok(moves)
+}
+
+/// Writes moves and their thinking times as a `.psq` file.
+pub fn write(moves: &[TimedMove]) -> String {
+    let mut content = format!("Piskvorky {0}x{0}, 11:11, 0\n", BOARD_SIZE);
+    for &((x, y), time) in moves {
+        content += &format!("{},{},{}\n", y + 1, x + 1, time);
+    }
+    content + "-1\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_moves_and_times() {
+        let content = "Piskvorky 15x15, 11:11, 0\n8,8,1200\n9,7,350\n 10,6, 0 \n-1\nengine.exe\n";
+        assert_eq!(
+            parse(content),
+            Ok(vec![((7, 7), 1200), ((6, 8), 350), ((5, 9), 0)])
+        );
+    }
+
+    #[test]
+    fn missing_and_extra_fields() {
+        // No time field, and the extra fields of newer Piskvork versions.
+        let content = "Piskvorky 15, 0:0, 1\n8,8\n9,7,350,1,0\n";
+        assert_eq!(parse(content), Ok(vec![((7, 7), 0), ((6, 8), 350)]));
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(parse("Gomoku 15x15\n8,8,0\n").is_err());
+        assert!(parse("Piskvorky 20x20, 11:11, 0\n8,8,0\n").is_err());
+        assert!(parse("Piskvorky 15x15, 11:11, 0\n16,8,0\n").is_err());
+        assert!(parse("Piskvorky 15x15, 11:11, 0\n8,0,0\n").is_err());
+        assert!(parse("Piskvorky 15x15, 11:11, 0\n8,8,0\n8,8,10\n").is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let moves = vec![((7, 7), 1200), ((6, 8), 0), ((14, 0), 999)];
+        assert_eq!(parse(&write(&moves)), Ok(moves));
+    }
+}
@@ -0,0 +1,216 @@
+//! Saved games: who played, the engine settings, the moves with their thinking times and
+//! the hints taken, stored as JSON. Alternative lines are kept as variations branching
+//! off the main line, so a record is a game tree.
+
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::{clock::Clock, notation, psq, report::MoveReport, weights::Weights};
+
+/// A board cell and whether the stone on it is black.
+pub type Stone = ((usize, usize), bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Player {
+    Human,
+    AI,
+}
+
+/// How a game ended other than on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Termination {
+    Time,
+    Resignation,
+    /// A draw offered and accepted.
+    Agreement,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineSettings {
+    pub depth: usize,
+    pub profile: String,
+    pub weights: Weights,
+    pub early_draw: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordMove {
+    /// The cell in standard notation, e.g. `h8`.
+    pub step: String,
+    /// Time spent on the move in milliseconds.
+    #[serde(default)]
+    pub time_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// An alternative line that branches off its parent line after `ply` of the parent's
+/// moves, with its own sub-variations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variation {
+    pub ply: usize,
+    pub moves: Vec<RecordMove>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: Vec<Variation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub black: Player,
+    pub white: Player,
+    /// `freestyle`, where five or more in a row wins, or `standard`, exactly five.
+    pub rule: String,
+    pub engine: EngineSettings,
+    /// The white engine of an AI vs AI game, `engine` then being black's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub white_engine: Option<EngineSettings>,
+    /// Start of the game in seconds since the Unix epoch.
+    pub date: u64,
+    /// `1-0`, `0-1` or `1/2-1/2` from black's point of view; absent while unfinished.
+    pub result: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<Termination>,
+    /// Black's and white's clocks at the end of the record, if the game was timed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clocks: Option<[Clock; 2]>,
+    pub moves: Vec<RecordMove>,
+    /// Move number and suggested cell of every hint taken.
+    #[serde(default)]
+    pub hints: Vec<(usize, String)>,
+    /// Stones on the board before the first move.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup_black: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub setup_white: Vec<String>,
+    /// Whether black plays the first of `moves`; when absent, black does if both sides
+    /// have as many setup stones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub black_first: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variations: Vec<Variation>,
+    /// Post-game analysis of the main line, one entry per move from the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub report: Vec<MoveReport>,
+}
+
+impl GameRecord {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        serde_json::from_str(content).map_err(|e| e.to_string())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Can not serialize game record")
+    }
+
+    /// The moves as board cells, or the first malformed move.
+    pub fn steps(&self) -> Result<Vec<(usize, usize)>, String> {
+        self.moves
+            .iter()
+            .map(|m| notation::parse_notation(&m.step).ok_or_else(|| m.step.clone()))
+            .collect()
+    }
+
+    /// Whether black plays the first move, by default when the setup is even.
+    pub fn black_first(&self) -> bool {
+        self.black_first
+            .unwrap_or(self.setup_black.len() == self.setup_white.len())
+    }
+
+    /// The setup stones, black's then white's, each with whether it is black.
+    pub fn opening(&self) -> Result<Vec<Stone>, String> {
+        let black = self.setup_black.iter().map(|step| (step, true));
+        let white = self.setup_white.iter().map(|step| (step, false));
+        black
+            .chain(white)
+            .map(|(step, black)| {
+                notation::parse_notation(step)
+                    .map(|cell| (cell, black))
+                    .ok_or_else(|| format!("malformed setup stone {:?}", step))
+            })
+            .collect()
+    }
+
+    /// The moves with their thinking times as a Piskvork `.psq` file.
+    pub fn to_psq(&self) -> Result<String, String> {
+        let steps = self.steps()?;
+        let times = self.moves.iter().map(|m| m.time_ms);
+        Ok(psq::write(
+            &steps.into_iter().zip(times).collect::<Vec<_>>(),
+        ))
+    }
+
+    /// The moves as a RenLib position string, e.g. `h8i9j10`.
+    pub fn to_position(&self) -> Result<String, String> {
+        Ok(notation::to_position(&self.steps()?))
+    }
+
+    pub fn file_name(&self) -> String {
+        let date: String = format_date(self.date)
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        format!("gobang-{}.json", date)
+    }
+}
+
+/// Reads the moves and thinking times of a game kept in another program's format: a
+/// Piskvork `.psq` file, or a position string such as `h8i9j10` or `h8 i9 j10`.
+pub fn import_moves(file_name: &str, content: &str) -> Result<Vec<psq::TimedMove>, String> {
+    if file_name.to_ascii_lowercase().ends_with(".psq") {
+        return psq::parse(content);
+    }
+    let steps = notation::parse_moves(content)
+        .filter(|steps| !steps.is_empty())
+        .ok_or("not a game record or position")?;
+    Ok(steps.into_iter().map(|step| (step, 0)).collect())
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Parses a `YYYY-MM-DD` date into seconds since the Unix epoch at midnight UTC.
+pub fn parse_date(s: &str) -> Option<u64> {
+    let mut parts = s.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.get(..2)?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days since 1970-01-01, after Howard Hinnant's `days_from_civil`.
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    u64::try_from((era * 146097 + doe - 719468) * 86400).ok()
+}
+
+/// Formats a Unix timestamp as `YYYY-MM-DD HH:MM` in UTC.
+pub fn format_date(secs: u64) -> String {
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60
+    )
+}
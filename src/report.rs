@@ -0,0 +1,301 @@
+//! Post-game analysis: every move is searched again and compared with the best move, and
+//! both sides are checked for forced wins by continuous fours (VCF).
+
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    ai::{AI, Outlook},
+    gui::BOARD_SIZE,
+    notation::to_notation,
+    record::Stone,
+    weights::Weights,
+};
+
+/// Attacking moves a VCF may take, and positions it may visit, before giving up.
+const VCF_DEPTH: usize = 10;
+const VCF_NODES: usize = 5000;
+
+type Board = [[u8; BOARD_SIZE]; BOARD_SIZE];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Class {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Class {
+    /// Classifies a move by how much it lowered the mover's expected result.
+    fn from_drop(drop: f32) -> Class {
+        if drop < 0.05 {
+            Class::Good
+        } else if drop < 0.1 {
+            Class::Inaccuracy
+        } else if drop < 0.2 {
+            Class::Mistake
+        } else {
+            Class::Blunder
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Best => "best",
+            Class::Good => "good",
+            Class::Inaccuracy => "inaccuracy",
+            Class::Mistake => "mistake",
+            Class::Blunder => "blunder",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveReport {
+    /// Score of the move played, from the mover's point of view.
+    pub score: i32,
+    /// The engine's choice in standard notation and its score.
+    pub best: String,
+    pub best_score: i32,
+    pub class: Class,
+    /// The mover had a forced win by continuous fours and let it go.
+    pub missed_win: bool,
+    /// The opponent threatened a forced win by continuous fours that the move left open
+    /// although the position could be held.
+    pub missed_defence: bool,
+}
+
+/// Maps a search score onto the expected result for the side it belongs to.
+pub fn win_probability(score: i32) -> f32 {
+    match Outlook::from_score(score) {
+        Some(Outlook::WinIn(_)) => 1.0,
+        Some(Outlook::LossIn(_)) => 0.0,
+        None => 1.0 / (1.0 + (-score as f32 / 2000.0).exp()),
+    }
+}
+
+/// Searches the position of `stones`, each with whether it is black, and judges
+/// `played`, a move of `mover_black`'s side, against the best move. Returns `None` if the
+/// search was cancelled.
+pub fn analyse_move(
+    stones: &[Stone],
+    played: (usize, usize),
+    mover_black: bool,
+    depth: usize,
+    exact_five: bool,
+    weights: &Weights,
+    cancel: &Arc<AtomicBool>,
+) -> Option<MoveReport> {
+    let mut ai = AI::new();
+    ai.ai_black = mover_black;
+    ai.exact_five = exact_five;
+    ai.set_weights(weights.clone());
+    ai.cancel = Some(cancel.clone());
+    let mut board: Board = [[0; BOARD_SIZE]; BOARD_SIZE];
+    for &((x, y), black) in stones {
+        if black == mover_black {
+            ai.ai_step(x, y);
+        } else {
+            ai.human_step(x, y);
+        }
+        board[x][y] = if black { 1 } else { 2 };
+    }
+    let candidates = ai.analyse(depth);
+    if cancel.load(Ordering::Relaxed) {
+        return None;
+    }
+    let Some(best) = candidates.first() else {
+        // Nothing to compare with on an empty board.
+        return Some(MoveReport {
+            score: 0,
+            best: to_notation(played),
+            best_score: 0,
+            class: Class::Best,
+            missed_win: false,
+            missed_defence: false,
+        });
+    };
+    let score = candidates
+        .iter()
+        .find(|c| c.step == played)
+        .map_or_else(|| ai.score_move(depth, played), |c| c.score);
+    let class = if played == best.step || score >= best.score {
+        Class::Best
+    } else {
+        Class::from_drop(win_probability(best.score) - win_probability(score))
+    };
+    let (mover, opponent) = if mover_black { (1, 2) } else { (2, 1) };
+    let wins = matches!(Outlook::from_score(score), Some(Outlook::WinIn(_)));
+    let missed_win = !wins && vcf(&board, mover, exact_five).is_some_and(|line| line[0] != played);
+    let threatened = vcf(&board, opponent, exact_five).is_some();
+    board[played.0][played.1] = mover;
+    let missed_defence = threatened
+        && !wins
+        && !matches!(Outlook::from_score(best.score), Some(Outlook::LossIn(_)))
+        && vcf(&board, opponent, exact_five).is_some();
+    Some(MoveReport {
+        score,
+        best: to_notation(best.step),
+        best_score: best.score,
+        class,
+        missed_win,
+        missed_defence,
+    })
+}
+
+const DIRECTIONS: [(isize, isize); 4] = [(0, 1), (1, 0), (1, 1), (1, -1)];
+
+fn offset((x, y): (usize, usize), (dx, dy): (isize, isize), n: isize) -> Option<(usize, usize)> {
+    let (x, y) = (x.checked_add_signed(dx * n)?, y.checked_add_signed(dy * n)?);
+    (x < BOARD_SIZE && y < BOARD_SIZE).then_some((x, y))
+}
+
+/// Whether a `colour` stone on the empty `cell` would complete five or more in a row,
+/// or with `exact_five` exactly five.
+fn makes_five(board: &Board, cell: (usize, usize), colour: u8, exact_five: bool) -> bool {
+    DIRECTIONS.iter().any(|&direction| {
+        let run = |sign: isize| {
+            (1..=5)
+                .take_while(|&n| {
+                    offset(cell, direction, sign * n).is_some_and(|(x, y)| board[x][y] == colour)
+                })
+                .count()
+        };
+        let stones = run(1) + run(-1);
+        if exact_five { stones == 4 } else { stones >= 4 }
+    })
+}
+
+/// Empty cells where `colour` would complete five, on the lines through `cell` or, if
+/// `cell` is `None`, anywhere.
+fn fives(
+    board: &Board,
+    colour: u8,
+    cell: Option<(usize, usize)>,
+    exact_five: bool,
+) -> Vec<(usize, usize)> {
+    let mut cells: Vec<(usize, usize)> = match cell {
+        Some(cell) => DIRECTIONS
+            .iter()
+            .flat_map(|&d| (-4..=4).filter_map(move |n| offset(cell, d, n)))
+            .collect(),
+        None => (0..BOARD_SIZE)
+            .flat_map(|x| (0..BOARD_SIZE).map(move |y| (x, y)))
+            .collect(),
+    };
+    cells.sort_unstable();
+    cells.dedup();
+    cells.retain(|&(x, y)| board[x][y] == 0 && makes_five(board, (x, y), colour, exact_five));
+    cells
+}
+
+/// Whether `cell` has at least three `colour` stones within four cells along one line,
+/// which any move making a four needs.
+fn may_make_four(board: &Board, cell: (usize, usize), colour: u8) -> bool {
+    DIRECTIONS.iter().any(|&d| {
+        (-4..=4)
+            .filter_map(|n| offset(cell, d, n))
+            .filter(|&(x, y)| board[x][y] == colour)
+            .count()
+            >= 3
+    })
+}
+
+/// A forced win for `colour`, to move, by continuous fours: the attacking moves, each
+/// answered by the only block, ending in five or in a double four.
+fn vcf(board: &Board, colour: u8, exact_five: bool) -> Option<Vec<(usize, usize)>> {
+    let mut board = *board;
+    let mut nodes = 0;
+    search_vcf(&mut board, colour, exact_five, VCF_DEPTH, &mut nodes)
+}
+
+fn search_vcf(
+    board: &mut Board,
+    colour: u8,
+    exact_five: bool,
+    depth: usize,
+    nodes: &mut usize,
+) -> Option<Vec<(usize, usize)>> {
+    if let Some(&win) = fives(board, colour, None, exact_five).first() {
+        return Some(vec![win]);
+    }
+    if depth == 0 || *nodes >= VCF_NODES {
+        return None;
+    }
+    let opponent = 3 - colour;
+    // A four of the opponent's has to be blocked, and the block must be a four too.
+    let candidates = match fives(board, opponent, None, exact_five).as_slice() {
+        [] => (0..BOARD_SIZE)
+            .flat_map(|x| (0..BOARD_SIZE).map(move |y| (x, y)))
+            .filter(|&(x, y)| board[x][y] == 0 && may_make_four(board, (x, y), colour))
+            .collect(),
+        &[threat] => vec![threat],
+        _ => return None,
+    };
+    for (x, y) in candidates {
+        *nodes += 1;
+        board[x][y] = colour;
+        let line = match fives(board, colour, Some((x, y)), exact_five).as_slice() {
+            [] => None,
+            &[(bx, by)] => {
+                board[bx][by] = opponent;
+                let line = search_vcf(board, colour, exact_five, depth - 1, nodes);
+                board[bx][by] = 0;
+                line
+            }
+            _ => Some(Vec::new()),
+        };
+        board[x][y] = 0;
+        if let Some(mut line) = line {
+            line.insert(0, (x, y));
+            return Some(line);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with `black` stones, the rest empty.
+    fn board(black: &[(usize, usize)]) -> Board {
+        let mut board: Board = [[0; BOARD_SIZE]; BOARD_SIZE];
+        for &(x, y) in black {
+            board[x][y] = 1;
+        }
+        board
+    }
+
+    #[test]
+    fn overlines_complete_five_only_in_freestyle() {
+        // The gap in a broken row of six.
+        let board = board(&[(7, 1), (7, 2), (7, 3), (7, 5), (7, 6)]);
+        assert_eq!(fives(&board, 1, None, false), [(7, 4)]);
+        assert_eq!(fives(&board, 1, None, true), []);
+        assert_eq!(vcf(&board, 1, false), Some(vec![(7, 4)]));
+        assert_eq!(vcf(&board, 1, true), None);
+    }
+
+    #[test]
+    fn exact_five_next_to_a_gap_still_wins() {
+        let board = board(&[(7, 1), (7, 2), (7, 3), (7, 4), (7, 6)]);
+        assert_eq!(fives(&board, 1, None, true), [(7, 0)]);
+        assert_eq!(fives(&board, 1, None, false), [(7, 0), (7, 5)]);
+        assert_eq!(vcf(&board, 1, true), Some(vec![(7, 0)]));
+    }
+
+    #[test]
+    fn finds_a_vcf_through_a_four() {
+        // An open four: two fives the opponent cannot both block.
+        let board = board(&[(7, 2), (7, 3), (7, 4)]);
+        assert_eq!(fives(&board, 1, None, true), []);
+        let line = vcf(&board, 1, true).unwrap();
+        assert!(matches!(line.as_slice(), [(7, 1) | (7, 5)]), "{:?}", line);
+    }
+}
@@ -0,0 +1,221 @@
+//! Stepping through a recorded game tree.
+//!
+//! The cursor follows one line of the tree: the main line, or a variation selected by
+//! `path`, each index picking a variation among those of the line before it. Moves played
+//! on the board during review are stored as new variations, so the record grows with
+//! the lines explored.
+
+use crate::{
+    clock::Clock,
+    gui::BOARD_SIZE,
+    notation::{parse_notation, to_notation},
+    record::{GameRecord, RecordMove, Stone, Variation},
+};
+
+pub struct Review {
+    pub record: GameRecord,
+    path: Vec<usize>,
+    /// Number of moves of the current line on the board.
+    pub ply: usize,
+    /// Setup stones, each with whether it is black.
+    opening: Vec<Stone>,
+}
+
+impl Review {
+    /// Starts at the final position of the main line.
+    pub fn new(record: GameRecord) -> Result<Self, String> {
+        let opening = record.opening()?;
+        record
+            .steps()
+            .map_err(|step| format!("malformed move {:?}", step))?;
+        let ply = record.moves.len();
+        Ok(Review {
+            record,
+            path: Vec::new(),
+            ply,
+            opening,
+        })
+    }
+
+    /// The variations along `path` with the absolute ply each one branches off at.
+    fn branches(&self, path: &[usize]) -> Vec<(usize, &Variation)> {
+        let (mut offset, mut variations) = (0, &self.record.variations);
+        let mut branches = Vec::new();
+        for &i in path {
+            let variation = &variations[i];
+            offset += variation.ply;
+            branches.push((offset, variation));
+            variations = &variation.variations;
+        }
+        branches
+    }
+
+    /// The moves of the line the cursor follows.
+    pub fn line(&self) -> Vec<RecordMove> {
+        let mut line = self.record.moves.clone();
+        for (offset, variation) in self.branches(&self.path) {
+            line.truncate(offset);
+            line.extend(variation.moves.iter().cloned());
+        }
+        line
+    }
+
+    fn steps(&self) -> Vec<(usize, usize)> {
+        self.line()
+            .iter()
+            .filter_map(|m| parse_notation(&m.step))
+            .collect()
+    }
+
+    /// Stones on the board at the cursor, `1` for black and `2` for white, numbered by
+    /// move with setup stones as move 0.
+    pub fn board(&self) -> [[(i32, usize); BOARD_SIZE]; BOARD_SIZE] {
+        let mut board = [[(0, 0); BOARD_SIZE]; BOARD_SIZE];
+        let black_first = self.record.black_first();
+        let numbered = self.opening.iter().map(|&(s, black)| (s, black, 0));
+        let played = self.steps().into_iter().take(self.ply).enumerate();
+        let played = played.map(|(i, s)| (s, i.is_multiple_of(2) == black_first, i + 1));
+        for ((x, y), black, number) in numbered.chain(played) {
+            board[x][y] = (if black { 1 } else { 2 }, number);
+        }
+        board
+    }
+
+    pub fn on_main_line(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    pub fn last_step(&self) -> Option<(usize, usize)> {
+        self.steps().get(self.ply.checked_sub(1)?).copied()
+    }
+
+    pub fn last_ply(&self) -> usize {
+        self.line().len()
+    }
+
+    pub fn go_to(&mut self, ply: usize) {
+        self.ply = ply.min(self.last_ply());
+    }
+
+    /// Lines other than the current one that branch off at the cursor, with their first
+    /// move.
+    pub fn alternatives(&self) -> Vec<(Vec<usize>, String)> {
+        let mut alternatives = Vec::new();
+        let (mut offset, mut variations) = (0, &self.record.variations);
+        let mut line = self.record.moves.clone();
+        for level in 0..=self.path.len() {
+            let prefix = &self.path[..level];
+            let leaves_at = self.path.get(level).map(|&i| offset + variations[i].ply);
+            if leaves_at == Some(self.ply)
+                && let Some(m) = line.get(self.ply)
+            {
+                alternatives.push((prefix.to_vec(), m.step.clone()));
+            }
+            if leaves_at.is_none_or(|at| self.ply <= at) {
+                for (i, variation) in variations.iter().enumerate() {
+                    if offset + variation.ply == self.ply
+                        && self.path.get(level) != Some(&i)
+                        && let Some(m) = variation.moves.first()
+                    {
+                        alternatives.push(([prefix, &[i]].concat(), m.step.clone()));
+                    }
+                }
+            }
+            if let Some(&i) = self.path.get(level) {
+                let variation = &variations[i];
+                offset += variation.ply;
+                line.truncate(offset);
+                line.extend(variation.moves.iter().cloned());
+                variations = &variation.variations;
+            }
+        }
+        alternatives
+    }
+
+    /// Follows another line, keeping the cursor where it is.
+    pub fn select(&mut self, path: Vec<usize>) {
+        self.path = path;
+        self.ply = self.ply.min(self.last_ply());
+    }
+
+    /// Plays `step` at the cursor: follows the line that already continues with it, or
+    /// starts a new variation there.
+    pub fn play(&mut self, step: (usize, usize)) {
+        let notation = to_notation(step);
+        let line = self.line();
+        if line.get(self.ply).is_some_and(|m| m.step == notation) {
+            self.ply += 1;
+            return;
+        }
+        if let Some((path, _)) = self
+            .alternatives()
+            .into_iter()
+            .find(|(_, first)| *first == notation)
+        {
+            self.path = path;
+            self.ply += 1;
+            return;
+        }
+        let new_move = RecordMove {
+            step: notation,
+            time_ms: 0,
+            comment: None,
+        };
+        // A variation being explored grows at its end instead of branching again.
+        if !self.path.is_empty() && self.ply == line.len() {
+            self.variation_mut(self.path.len()).moves.push(new_move);
+            self.ply += 1;
+            return;
+        }
+        // Attach to the deepest line of the path that the cursor is on; a move replacing
+        // the first one of a variation becomes its sibling.
+        let offsets: Vec<usize> = self.branches(&self.path).iter().map(|&(o, _)| o).collect();
+        let level = offsets.iter().take_while(|&&o| o < self.ply).count();
+        let offset = level.checked_sub(1).map_or(0, |l| offsets[l]);
+        let variation = Variation {
+            ply: self.ply - offset,
+            moves: vec![new_move],
+            variations: Vec::new(),
+        };
+        let variations = if level == 0 {
+            &mut self.record.variations
+        } else {
+            self.path.truncate(level);
+            &mut self.variation_mut(level).variations
+        };
+        variations.push(variation);
+        let index = variations.len() - 1;
+        self.path.truncate(level);
+        self.path.push(index);
+        self.ply += 1;
+    }
+
+    /// The variation selected by the first `depth` indices of the path.
+    fn variation_mut(&mut self, depth: usize) -> &mut Variation {
+        let mut variation = &mut self.record.variations[self.path[0]];
+        for &i in &self.path[1..depth] {
+            variation = &mut variation.variations[i];
+        }
+        variation
+    }
+
+    /// The record of the current line up to the cursor, to be continued as a game with
+    /// fresh clocks.
+    pub fn position(&self) -> GameRecord {
+        let mut moves = self.line();
+        moves.truncate(self.ply);
+        GameRecord {
+            moves,
+            result: None,
+            termination: None,
+            clocks: self
+                .record
+                .clocks
+                .map(|clocks| clocks.map(|clock| Clock::new(clock.control))),
+            variations: Vec::new(),
+            hints: Vec::new(),
+            report: Vec::new(),
+            ..self.record.clone()
+        }
+    }
+}
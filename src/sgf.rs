@@ -0,0 +1,618 @@
+//! Smart Game Format (`FF[4]`) game trees for gomoku (`GM[4]`), as exchanged with Go and
+//! gomoku tools.
+//!
+//! Points are two letters, the column then the row counted from the top-left corner, so
+//! the centre of the board is `hh`. The main line follows the first child of every node
+//! and the other children become variations. Comments (`C`) are kept on every node;
+//! setup stones (`AB`, `AW`, `AE`) only in the nodes before the first move, which may
+//! leave either side to move (`PL`).
+
+use std::slice;
+
+use crate::{
+    gui::BOARD_SIZE,
+    notation::{parse_notation, to_notation},
+    record::{self, GameRecord, Player, RecordMove, Termination, Variation},
+    report::{Class, MoveReport},
+};
+
+#[derive(Default)]
+struct Node {
+    properties: Vec<(String, Vec<String>)>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn values(&self, key: &str) -> &[String] {
+        self.properties
+            .iter()
+            .find(|(k, _)| k == key)
+            .map_or(&[], |(_, values)| values)
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values(key).first().map(String::as_str)
+    }
+
+    fn set(&mut self, key: &str, values: Vec<String>) {
+        if !values.is_empty() {
+            self.properties.push((key.to_string(), values));
+        }
+    }
+}
+
+fn to_point((x, y): (usize, usize)) -> String {
+    format!("{}{}", (b'a' + y as u8) as char, (b'a' + x as u8) as char)
+}
+
+fn parse_point(s: &str) -> Result<(usize, usize), String> {
+    match *s.as_bytes() {
+        [column @ b'a'..=b'z', row @ b'a'..=b'z']
+            if ((column - b'a') as usize) < BOARD_SIZE && ((row - b'a') as usize) < BOARD_SIZE =>
+        {
+            Ok(((row - b'a') as usize, (column - b'a') as usize))
+        }
+        [] => Err("passes are not supported".to_string()),
+        _ => Err(format!("point {:?} is off the board", s)),
+    }
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    fn eat(&mut self, c: char) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `( ;node ;node ... (subtree) (subtree) ... )`, the subtrees hanging off the last
+    /// node of the sequence.
+    fn tree(&mut self) -> Result<Node, String> {
+        if !self.eat('(') {
+            return Err("expected '('".to_string());
+        }
+        let mut sequence = Vec::new();
+        while self.eat(';') {
+            sequence.push(self.node()?);
+        }
+        let mut children = Vec::new();
+        while self.rest.trim_start().starts_with('(') {
+            children.push(self.tree()?);
+        }
+        if !self.eat(')') {
+            return Err("expected ')'".to_string());
+        }
+        let mut node = sequence.pop().ok_or("empty game tree")?;
+        node.children = children;
+        while let Some(mut parent) = sequence.pop() {
+            parent.children = vec![node];
+            node = parent;
+        }
+        Ok(node)
+    }
+
+    fn node(&mut self) -> Result<Node, String> {
+        let mut node = Node::default();
+        loop {
+            self.rest = self.rest.trim_start();
+            let len = self
+                .rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(self.rest.len());
+            if len == 0 {
+                return Ok(node);
+            }
+            // Older files spell identifiers out in mixed case, e.g. `AddBlack` for `AB`.
+            let key: String = self.rest[..len]
+                .chars()
+                .filter(char::is_ascii_uppercase)
+                .collect();
+            self.rest = &self.rest[len..];
+            let mut values = Vec::new();
+            while self.eat('[') {
+                values.push(self.value()?);
+            }
+            if values.is_empty() {
+                return Err(format!("property {} has no value", key));
+            }
+            node.properties.push((key, values));
+        }
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                ']' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Ok(value);
+                }
+                '\\' => match chars.next() {
+                    // An escaped line break is a soft break and disappears.
+                    Some((_, '\n')) => {}
+                    Some((_, c)) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        Err("unterminated property value".to_string())
+    }
+}
+
+const SETUP: [&str; 3] = ["AB", "AW", "AE"];
+
+/// The colour of the node's move, `true` for black.
+fn move_color(node: &Node) -> Option<bool> {
+    match (node.get("B"), node.get("W")) {
+        (Some(_), _) => Some(true),
+        (None, Some(_)) => Some(false),
+        (None, None) => None,
+    }
+}
+
+/// The colour of the first move along the first children from `node`.
+fn first_move_color(mut node: &Node) -> Option<bool> {
+    loop {
+        if let Some(color) = move_color(node) {
+            return Some(color);
+        }
+        node = node.children.first()?;
+    }
+}
+
+/// Adds the node's setup stones to `black` and `white` and removes its setup properties.
+fn take_setup(
+    node: &mut Node,
+    black: &mut Vec<(usize, usize)>,
+    white: &mut Vec<(usize, usize)>,
+) -> Result<(), String> {
+    let points = |key: &str| -> Result<Vec<(usize, usize)>, String> {
+        node.values(key).iter().map(|p| parse_point(p)).collect()
+    };
+    let (added_black, added_white, erased) = (points("AB")?, points("AW")?, points("AE")?);
+    black.retain(|s| !erased.contains(s) && !added_white.contains(s));
+    white.retain(|s| !erased.contains(s) && !added_black.contains(s));
+    for (stones, added) in [(black, added_black), (white, added_white)] {
+        for step in added {
+            if !stones.contains(&step) {
+                stones.push(step);
+            }
+        }
+    }
+    node.properties
+        .retain(|(key, _)| !SETUP.contains(&key.as_str()));
+    Ok(())
+}
+
+/// The node's move, which must be `black`'s if black is to move.
+fn read_move(node: &Node, black: bool) -> Result<Option<RecordMove>, String> {
+    if SETUP.iter().any(|key| node.get(key).is_some()) {
+        return Err("setup stones are only supported before the first move".to_string());
+    }
+    if node.get("B").is_some() && node.get("W").is_some() {
+        return Err("a node plays both colours".to_string());
+    }
+    let Some(color) = move_color(node) else {
+        return Ok(None);
+    };
+    let step = parse_point(node.get(if color { "B" } else { "W" }).unwrap_or_default())?;
+    if color != black {
+        return Err(format!("{} is played out of turn", to_notation(step)));
+    }
+    Ok(Some(RecordMove {
+        step: to_notation(step),
+        time_ms: 0,
+        comment: node.get("C").map(str::to_string),
+    }))
+}
+
+/// Follows the first children from `children` as the line's moves, `black` telling
+/// whether black plays first; every other child starts a variation. Nodes without a move
+/// are passed through.
+fn read_line(
+    mut children: &[Node],
+    mut black: bool,
+) -> Result<(Vec<RecordMove>, Vec<Variation>), String> {
+    let (mut moves, mut variations) = (Vec::new(), Vec::new());
+    while let Some((first, others)) = children.split_first() {
+        for other in others {
+            let (alternative, sub_variations) = read_line(slice::from_ref(other), black)?;
+            if !alternative.is_empty() {
+                variations.push(Variation {
+                    ply: moves.len(),
+                    moves: alternative,
+                    variations: sub_variations,
+                });
+            }
+        }
+        if let Some(m) = read_move(first, black)? {
+            moves.push(m);
+            black = !black;
+        }
+        children = &first.children;
+    }
+    Ok((moves, variations))
+}
+
+/// Reads the first game of an SGF collection. Whatever SGF does not carry, such as the
+/// engine settings, is taken from `template`.
+pub fn parse(content: &str, template: GameRecord) -> Result<GameRecord, String> {
+    let start = content.find('(').ok_or("not an SGF file")?;
+    let mut root = Parser {
+        rest: &content[start..],
+    }
+    .tree()?;
+    if root.get("GM").is_some_and(|gm| gm.trim() != "4") {
+        return Err("not a gomoku game (GM[4])".to_string());
+    }
+    if let Some(size) = root.get("SZ")
+        && size.trim().parse() != Ok(BOARD_SIZE)
+    {
+        return Err(format!(
+            "only {0}x{0} boards are supported, not {1}",
+            BOARD_SIZE, size
+        ));
+    }
+    let has_move = move_color(&root).is_some();
+    let comment = if has_move {
+        None
+    } else {
+        root.get("C").map(str::to_string)
+    };
+    let to_play = match root.get("PL").map(str::trim) {
+        Some("B") => Some(true),
+        Some("W") => Some(false),
+        _ => None,
+    };
+    // Setup stones may be spread over the nodes leading to the first move.
+    let (mut setup_black, mut setup_white) = (Vec::new(), Vec::new());
+    let mut start = &mut root;
+    loop {
+        take_setup(start, &mut setup_black, &mut setup_white)?;
+        if move_color(start).is_some() || start.children.len() != 1 {
+            break;
+        }
+        start = &mut start.children[0];
+    }
+    let black_first = first_move_color(start)
+        .or(to_play)
+        .unwrap_or(setup_black.len() == setup_white.len());
+    let (moves, variations) = if move_color(start).is_some() {
+        read_line(slice::from_ref(start), black_first)?
+    } else {
+        read_line(&start.children, black_first)?
+    };
+    let player = |key: &str| match root.get(key) {
+        Some("Human") => Some(Player::Human),
+        Some("AI") => Some(Player::AI),
+        _ => None,
+    };
+    let (black, white) = match (player("PB"), player("PW")) {
+        (Some(black), Some(white)) => (black, white),
+        // A game between other players is continued by the human from the side to move.
+        _ if moves.len().is_multiple_of(2) == black_first => (Player::Human, Player::AI),
+        _ => (Player::AI, Player::Human),
+    };
+    let result = root.get("RE").and_then(|re| match re.trim() {
+        re if re.starts_with("B+") => Some("1-0"),
+        re if re.starts_with("W+") => Some("0-1"),
+        "0" | "Draw" => Some("1/2-1/2"),
+        _ => None,
+    });
+    // `B+T`/`W+R`, or spelled out as in `B+Time` or `W+Resign`.
+    let termination = root.get("RE").and_then(|re| match re.trim().get(2..3)? {
+        "T" => Some(Termination::Time),
+        "R" => Some(Termination::Resignation),
+        _ => None,
+    });
+    Ok(GameRecord {
+        black,
+        white,
+        rule: root.get("RU").map_or(template.rule.clone(), str::to_string),
+        date: root
+            .get("DT")
+            .and_then(record::parse_date)
+            .unwrap_or(template.date),
+        result: result.map(str::to_string),
+        termination,
+        moves,
+        hints: Vec::new(),
+        black_first: (black_first != (setup_black.len() == setup_white.len()))
+            .then_some(black_first),
+        setup_black: setup_black.into_iter().map(to_notation).collect(),
+        setup_white: setup_white.into_iter().map(to_notation).collect(),
+        comment,
+        variations,
+        report: Vec::new(),
+        ..template
+    })
+}
+
+/// Move annotations for the post-game analysis: `TE` for the best move, `DO` for an
+/// inaccuracy and `BM` for a mistake or, doubled, a blunder.
+fn annotate(node: &mut Node, report: &MoveReport) {
+    let (key, value) = match report.class {
+        Class::Best => ("TE", "1"),
+        Class::Good => return,
+        Class::Inaccuracy => ("DO", ""),
+        Class::Mistake => ("BM", "1"),
+        Class::Blunder => ("BM", "2"),
+    };
+    node.set(key, vec![value.to_string()]);
+}
+
+fn line(
+    moves: &[RecordMove],
+    variations: &[Variation],
+    report: &[MoveReport],
+    ply: usize,
+    black: bool,
+) -> Vec<Node> {
+    let mut children = Vec::new();
+    if let Some(m) = moves.get(ply) {
+        let mut node = Node::default();
+        let point = parse_notation(&m.step).map_or_else(String::new, to_point);
+        node.set(if black { "B" } else { "W" }, vec![point]);
+        node.set("C", m.comment.iter().cloned().collect());
+        if let Some(report) = report.get(ply) {
+            annotate(&mut node, report);
+        }
+        node.children = line(moves, variations, report, ply + 1, !black);
+        children.push(node);
+    }
+    for variation in variations.iter().filter(|v| v.ply == ply) {
+        children.extend(line(&variation.moves, &variation.variations, &[], 0, black));
+    }
+    children
+}
+
+fn write_node(out: &mut String, node: &Node) {
+    out.push(';');
+    for (key, values) in &node.properties {
+        out.push_str(key);
+        for value in values {
+            out.push('[');
+            out.push_str(&value.replace('\\', "\\\\").replace(']', "\\]"));
+            out.push(']');
+        }
+    }
+    match node.children.as_slice() {
+        [child] => write_node(out, child),
+        children => {
+            for child in children {
+                out.push_str("\n(");
+                write_node(out, child);
+                out.push(')');
+            }
+        }
+    }
+}
+
+pub fn write(record: &GameRecord) -> String {
+    let mut root = Node::default();
+    root.set("FF", vec!["4".to_string()]);
+    root.set("GM", vec!["4".to_string()]);
+    root.set("SZ", vec![BOARD_SIZE.to_string()]);
+    root.set("CA", vec!["UTF-8".to_string()]);
+    root.set(
+        "AP",
+        vec![format!("gobang-rs:{}", env!("CARGO_PKG_VERSION"))],
+    );
+    root.set("RU", vec![record.rule.clone()]);
+    root.set("PB", vec![format!("{:?}", record.black)]);
+    root.set("PW", vec![format!("{:?}", record.white)]);
+    root.set(
+        "DT",
+        vec![record::format_date(record.date)[..10].to_string()],
+    );
+    let reason = match record.termination {
+        Some(Termination::Time) => "T",
+        Some(Termination::Resignation) => "R",
+        Some(Termination::Agreement) | None => "",
+    };
+    let result = record.result.as_deref().and_then(|result| match result {
+        "1-0" => Some(format!("B+{}", reason)),
+        "0-1" => Some(format!("W+{}", reason)),
+        "1/2-1/2" => Some("0".to_string()),
+        _ => None,
+    });
+    root.set("RE", result.into_iter().collect());
+    root.set("C", record.comment.iter().cloned().collect());
+    let points = |stones: &[String]| -> Vec<String> {
+        stones
+            .iter()
+            .filter_map(|s| parse_notation(s).map(to_point))
+            .collect()
+    };
+    root.set("AB", points(&record.setup_black));
+    root.set("AW", points(&record.setup_white));
+    let black_first = record.black_first();
+    if record.black_first.is_some() {
+        root.set("PL", vec![if black_first { "B" } else { "W" }.to_string()]);
+    }
+    root.children = line(
+        &record.moves,
+        &record.variations,
+        &record.report,
+        0,
+        black_first,
+    );
+    let mut out = String::from("(");
+    write_node(&mut out, &root);
+    out + ")\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{record::EngineSettings, weights::Weights};
+
+    fn template() -> GameRecord {
+        GameRecord {
+            black: Player::Human,
+            white: Player::AI,
+            rule: "freestyle".to_string(),
+            engine: EngineSettings {
+                depth: 3,
+                profile: "Balanced".to_string(),
+                weights: Weights::default(),
+                early_draw: true,
+            },
+            white_engine: None,
+            date: 0,
+            result: None,
+            termination: None,
+            clocks: None,
+            moves: Vec::new(),
+            hints: Vec::new(),
+            setup_black: Vec::new(),
+            setup_white: Vec::new(),
+            black_first: None,
+            comment: None,
+            variations: Vec::new(),
+            report: Vec::new(),
+        }
+    }
+
+    fn read(content: &str) -> GameRecord {
+        parse(content, template()).unwrap()
+    }
+
+    fn steps(moves: &[RecordMove]) -> Vec<&str> {
+        moves.iter().map(|m| m.step.as_str()).collect()
+    }
+
+    fn record_move(step: &str) -> RecordMove {
+        RecordMove {
+            step: step.to_string(),
+            time_ms: 0,
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn reads_escapes_and_soft_breaks() {
+        let record = read("(;FF[4]GM[4]SZ[15]C[a \\] b \\\\ c\\\nd];B[hh]C[first\nline];W[ih])");
+        assert_eq!(record.comment.as_deref(), Some("a ] b \\ cd"));
+        assert_eq!(steps(&record.moves), ["h8", "i8"]);
+        assert_eq!(record.moves[0].comment.as_deref(), Some("first\nline"));
+    }
+
+    #[test]
+    fn reads_multi_value_and_long_identifiers() {
+        let record = read("(;GaMe[4] SiZe[15] AddBlack[hh] [ii] AddWhite[ih]\n;W[jj]C[reply])");
+        assert_eq!(record.setup_black, ["h8", "i7"]);
+        assert_eq!(record.setup_white, ["i8"]);
+        assert!(!record.black_first());
+        assert_eq!(steps(&record.moves), ["j6"]);
+        assert_eq!(record.moves[0].comment.as_deref(), Some("reply"));
+    }
+
+    #[test]
+    fn reads_variations_and_results() {
+        let record = read("(;GM[4]RE[W+Resign];B[hh](;W[ii];B[jj])(;W[gg](;B[ff])(;B[ee])))");
+        assert_eq!(steps(&record.moves), ["h8", "i7", "j6"]);
+        assert_eq!(record.result.as_deref(), Some("0-1"));
+        assert_eq!(record.termination, Some(Termination::Resignation));
+        let [variation] = record.variations.as_slice() else {
+            panic!("{:?}", record.variations);
+        };
+        assert_eq!(variation.ply, 1);
+        assert_eq!(steps(&variation.moves), ["g9", "f10"]);
+        assert_eq!(variation.variations.len(), 1);
+        assert_eq!(variation.variations[0].ply, 1);
+        assert_eq!(steps(&variation.variations[0].moves), ["e11"]);
+    }
+
+    #[test]
+    fn round_trips_a_game_tree() {
+        let mut record = template();
+        record.result = Some("1-0".to_string());
+        record.termination = Some(Termination::Time);
+        record.comment = Some("brackets ] and \\ survive".to_string());
+        record.moves = ["h8", "i9", "j10", "k11"].map(record_move).to_vec();
+        record.moves[1].comment = Some("a comment".to_string());
+        record.variations = vec![
+            Variation {
+                ply: 1,
+                moves: ["h9", "g8"].map(record_move).to_vec(),
+                variations: vec![Variation {
+                    ply: 1,
+                    moves: vec![record_move("f8")],
+                    variations: Vec::new(),
+                }],
+            },
+            Variation {
+                ply: 3,
+                moves: vec![record_move("l12")],
+                variations: Vec::new(),
+            },
+        ];
+        let read = read(&write(&record));
+        assert_eq!(steps(&read.moves), steps(&record.moves));
+        assert_eq!(read.moves[1].comment, record.moves[1].comment);
+        assert_eq!(read.comment, record.comment);
+        assert_eq!(read.result, record.result);
+        assert_eq!(read.termination, record.termination);
+        assert_eq!(read.variations.len(), 2);
+        for (read, written) in read.variations.iter().zip(&record.variations) {
+            assert_eq!(read.ply, written.ply);
+            assert_eq!(steps(&read.moves), steps(&written.moves));
+            assert_eq!(read.variations.len(), written.variations.len());
+        }
+        assert_eq!(steps(&read.variations[0].variations[0].moves), ["f8"]);
+    }
+
+    #[test]
+    fn accepts_any_setup_with_the_side_to_move() {
+        let record = read("(;GM[4]SZ[15]AB[hh][ih][jh]PL[W])");
+        assert_eq!(record.setup_black.len(), 3);
+        assert!(record.setup_white.is_empty());
+        assert!(!record.black_first());
+        assert_eq!(record.opening().unwrap().len(), 3);
+
+        let record = read("(;GM[4]AB[hh][ih][jh];B[kh];W[hg])");
+        assert_eq!(record.black_first, Some(true));
+        assert_eq!(steps(&record.moves), ["k8", "h9"]);
+        let read = read(&write(&record));
+        assert_eq!(read.setup_black, record.setup_black);
+        assert!(read.black_first());
+        assert_eq!(steps(&read.moves), ["k8", "h9"]);
+    }
+
+    #[test]
+    fn reads_setup_nodes_before_the_first_move() {
+        let record = read("(;GM[4];AB[hh]AW[ii];AE[hh]AB[jj]C[set up];B[kk])");
+        assert_eq!(record.setup_black, ["j6"]);
+        assert_eq!(record.setup_white, ["i7"]);
+        assert_eq!(steps(&record.moves), ["k5"]);
+    }
+
+    #[test]
+    fn rejects_malformed_games() {
+        for content in [
+            "(;GM[4];B[hh];B[ii])",
+            "(;GM[4];B[hh](;W[ii])(;B[jj]))",
+            "(;GM[4];B[hh];AB[ii])",
+            "(;GM[4];B[hh]W[ii])",
+            "(;GM[4];B[zz])",
+            "(;GM[4];B[])",
+            "(;GM[1];B[hh])",
+            "(;GM[4]SZ[19];B[hh])",
+            "(;GM[4];B[hh]",
+            "(;GM[4]C[unterminated)",
+        ] {
+            assert!(parse(content, template()).is_err(), "{}", content);
+        }
+    }
+}
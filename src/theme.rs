@@ -0,0 +1,254 @@
+//! Board and stone themes: the built-in ones and user theme files.
+
+use eframe::egui::{Color32, Painter, Pos2, Stroke, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// How the last move is marked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Marker {
+    #[default]
+    Ring,
+    Dot,
+    Cross,
+    Square,
+    None,
+}
+
+impl Marker {
+    pub const ALL: [Marker; 5] = [
+        Marker::Ring,
+        Marker::Dot,
+        Marker::Cross,
+        Marker::Square,
+        Marker::None,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Marker::Ring => "Ring",
+            Marker::Dot => "Dot",
+            Marker::Cross => "Cross",
+            Marker::Square => "Square",
+            Marker::None => "None",
+        }
+    }
+}
+
+/// The Okabe-Ito palette, whose colours stay apart with any colour vision.
+pub const SAFE_COLORS: [(&str, [u8; 3]); 7] = [
+    ("Orange", [230, 159, 0]),
+    ("Sky blue", [86, 180, 233]),
+    ("Bluish green", [0, 158, 115]),
+    ("Yellow", [240, 228, 66]),
+    ("Blue", [0, 114, 178]),
+    ("Vermillion", [213, 94, 0]),
+    ("Reddish purple", [204, 121, 167]),
+];
+
+/// Colours are `[r, g, b]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    pub board: [u8; 3],
+    /// Grid lines, star points and edge labels.
+    pub lines: [u8; 3],
+    pub black: [u8; 3],
+    pub white: [u8; 3],
+    /// Stones are drawn with a shadow and a highlight instead of flat.
+    #[serde(default)]
+    pub shaded: bool,
+    #[serde(default)]
+    pub marker: Marker,
+    pub marker_color: [u8; 3],
+    /// Hints and the analysis heatmap avoid telling things apart by red and green.
+    #[serde(default)]
+    pub safe_colors: bool,
+}
+
+impl Theme {
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme {
+                name: "Wood".to_string(),
+                board: [222, 184, 135],
+                lines: [60, 40, 20],
+                black: [20, 20, 20],
+                white: [245, 245, 240],
+                shaded: true,
+                marker: Marker::Ring,
+                marker_color: [220, 30, 30],
+                safe_colors: false,
+            },
+            Theme {
+                name: "Dark".to_string(),
+                board: [40, 44, 52],
+                lines: [140, 150, 160],
+                black: [10, 10, 12],
+                white: [210, 215, 220],
+                shaded: true,
+                marker: Marker::Dot,
+                marker_color: [255, 170, 0],
+                safe_colors: false,
+            },
+            Theme {
+                name: "High contrast".to_string(),
+                board: [255, 255, 255],
+                lines: [0, 0, 0],
+                black: [0, 0, 0],
+                white: [255, 255, 255],
+                shaded: false,
+                marker: Marker::Square,
+                marker_color: [0, 90, 255],
+                safe_colors: false,
+            },
+            Theme {
+                name: "Colour-blind safe".to_string(),
+                board: [222, 184, 135],
+                lines: [60, 40, 20],
+                black: [20, 20, 20],
+                white: [245, 245, 240],
+                shaded: true,
+                marker: Marker::Cross,
+                marker_color: SAFE_COLORS[0].1,
+                safe_colors: true,
+            },
+        ]
+    }
+
+    /// Parses a theme file, choosing TOML or JSON by the file name's extension.
+    pub fn parse(file_name: &str, content: &str) -> Result<Self, String> {
+        if file_name.ends_with(".toml") {
+            toml::from_str(content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(content).map_err(|e| e.to_string())
+        }
+    }
+
+    pub fn board(&self) -> Color32 {
+        rgb(self.board)
+    }
+
+    pub fn lines(&self) -> Color32 {
+        rgb(self.lines)
+    }
+
+    pub fn stone(&self, black: bool) -> Color32 {
+        rgb(if black { self.black } else { self.white })
+    }
+
+    /// Colour of the last-move marker and the winning line.
+    pub fn highlight(&self) -> Color32 {
+        rgb(self.marker_color)
+    }
+
+    /// Colour of the hinted move.
+    pub fn hint(&self) -> Color32 {
+        if self.safe_colors {
+            rgb(SAFE_COLORS[4].1)
+        } else {
+            Color32::from_rgb(0, 160, 0)
+        }
+    }
+
+    /// Colour of the expected reply to the hinted move.
+    pub fn reply(&self) -> Color32 {
+        if self.safe_colors {
+            rgb(SAFE_COLORS[6].1)
+        } else {
+            Color32::from_rgb(0, 90, 200)
+        }
+    }
+
+    /// Heatmap colour of a move with win probability `p`: red to green, or vermillion
+    /// to blue with safe colours.
+    pub fn outcome(&self, p: f32) -> Color32 {
+        let (r, g, b) = if self.safe_colors {
+            let color = lerp(rgb(SAFE_COLORS[5].1), rgb(SAFE_COLORS[4].1), p);
+            (color.r(), color.g(), color.b())
+        } else {
+            (
+                ((2.0 * (1.0 - p)).min(1.0) * 255.0) as u8,
+                ((2.0 * p).min(1.0) * 255.0) as u8,
+                0,
+            )
+        };
+        Color32::from_rgba_unmultiplied(r, g, b, 110)
+    }
+
+    /// Colour of text written on a stone.
+    pub fn on_stone(&self, black: bool) -> Color32 {
+        self.stone(!black)
+    }
+
+    pub fn paint_stone(&self, painter: &Painter, center: Pos2, radius: f32, black: bool) {
+        let color = self.stone(black);
+        if !self.shaded {
+            painter.circle_filled(center, radius, color);
+            painter.circle_stroke(center, radius, Stroke::new(1.0, self.lines()));
+            return;
+        }
+        painter.circle_filled(
+            center + Vec2::splat(radius / 8.0),
+            radius,
+            Color32::from_black_alpha(70),
+        );
+        painter.circle_filled(center, radius, color.gamma_multiply(0.8));
+        // Lighter discs towards the top left, the last one the highlight.
+        let light = if black {
+            Color32::from_gray(120)
+        } else {
+            Color32::WHITE
+        };
+        const STEPS: usize = 6;
+        for i in 1..=STEPS {
+            let t = i as f32 / STEPS as f32;
+            let shift = Vec2::splat(-radius * 0.35 * t);
+            let fill = lerp(color.gamma_multiply(0.8), light, t * t * 0.8);
+            painter.circle_filled(center + shift, radius * (1.0 - 0.8 * t), fill);
+        }
+    }
+
+    pub fn paint_marker(&self, painter: &Painter, center: Pos2, radius: f32) {
+        let color = self.highlight();
+        let stroke = Stroke::new((radius / 6.0).max(2.0), color);
+        match self.marker {
+            Marker::Ring => {
+                painter.circle_stroke(center, radius, stroke);
+            }
+            Marker::Dot => {
+                painter.circle_filled(center, radius / 3.0, color);
+            }
+            Marker::Cross => {
+                let d = radius / 2.0;
+                painter.line_segment([center - Vec2::splat(d), center + Vec2::splat(d)], stroke);
+                painter.line_segment(
+                    [center + Vec2::new(-d, d), center + Vec2::new(d, -d)],
+                    stroke,
+                );
+            }
+            Marker::Square => {
+                let half = Vec2::splat(radius / 3.0);
+                painter.rect_filled(
+                    eframe::egui::Rect::from_min_max(center - half, center + half),
+                    0.0,
+                    color,
+                );
+            }
+            Marker::None => {}
+        }
+    }
+}
+
+fn rgb([r, g, b]: [u8; 3]) -> Color32 {
+    Color32::from_rgb(r, g, b)
+}
+
+fn lerp(from: Color32, to: Color32, t: f32) -> Color32 {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+    Color32::from_rgb(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
+}
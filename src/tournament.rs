@@ -0,0 +1,372 @@
+//! Headless engine-vs-engine matches with Elo and SPRT reporting.
+//!
+//! Engines are described as `depth=3,movetime=500,weights=tuned.toml`; every opening is
+//! played twice with colours swapped so that neither engine profits from moving first.
+//! A `rule=standard` in either spec makes the whole match use exactly-five rules.
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+use web_time::Duration;
+
+use rand::Rng;
+
+use crate::{
+    ai::{AI, GameState},
+    gui::BOARD_SIZE,
+    notation,
+    tune::CorpusGame,
+    weights::{Profile, Weights},
+};
+
+#[derive(Clone)]
+struct EngineConfig {
+    depth: usize,
+    movetime: Option<Duration>,
+    weights: Weights,
+    /// Whether only exactly five wins, if the spec sets a rule.
+    exact_five: Option<bool>,
+}
+
+impl EngineConfig {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let mut config = EngineConfig {
+            depth: 2,
+            movetime: None,
+            weights: Weights::default(),
+            exact_five: None,
+        };
+        for option in spec.split(',').filter(|o| !o.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("malformed engine option {:?}", option))?;
+            match key {
+                "depth" => config.depth = value.parse().map_err(|_| "depth must be a number")?,
+                "movetime" => {
+                    let ms = value.parse().map_err(|_| "movetime must be milliseconds")?;
+                    config.movetime = Some(Duration::from_millis(ms));
+                }
+                "profile" => {
+                    config.weights = Profile::BUILTIN
+                        .into_iter()
+                        .find(|p| p.name().eq_ignore_ascii_case(value))
+                        .ok_or_else(|| format!("unknown profile {:?}", value))?
+                        .weights();
+                }
+                "weights" => {
+                    let content = fs::read_to_string(value).map_err(|e| e.to_string())?;
+                    config.weights = Weights::parse(value, &content)?;
+                }
+                "rule" => {
+                    config.exact_five = match value {
+                        "freestyle" => Some(false),
+                        "standard" => Some(true),
+                        _ => return Err(format!("unknown rule {:?}", value)),
+                    };
+                }
+                _ => return Err(format!("unknown engine option {:?}", key)),
+            }
+        }
+        Ok(config)
+    }
+
+    fn engine(&self, black: bool, exact_five: bool) -> AI {
+        let mut ai = AI::new();
+        ai.depth = self.depth;
+        ai.exact_five = exact_five;
+        ai.time_limit = self.movetime;
+        ai.ai_black = black;
+        ai.set_weights(self.weights.clone());
+        ai
+    }
+}
+
+/// Plays one game to completion; the opening is placed on the board as is.
+fn play(
+    black: &EngineConfig,
+    white: &EngineConfig,
+    opening: &[(usize, usize)],
+    exact_five: bool,
+) -> CorpusGame {
+    let mut engines = [
+        black.engine(true, exact_five),
+        white.engine(false, exact_five),
+    ];
+    let mut moves = Vec::new();
+    for &(x, y) in opening {
+        let side = moves.len() % 2;
+        engines[side].ai_step(x, y);
+        engines[1 - side].human_step(x, y);
+        moves.push((x, y));
+    }
+    loop {
+        let side = moves.len() % 2;
+        // A full board ends the game as a draw before this.
+        let (x, y) = engines[side].ai().expect("no empty cell left");
+        engines[1 - side].human_step(x, y);
+        moves.push((x, y));
+        if engines[side].is_game_over() {
+            let result = match engines[side].state {
+                GameState::Draw => 0.5,
+                _ if side == 0 => 1.0,
+                _ => 0.0,
+            };
+            return CorpusGame { moves, result };
+        }
+    }
+}
+
+/// Starts at the centre and adds `stones - 1` random stones within two cells of it.
+fn random_opening(stones: usize) -> Vec<(usize, usize)> {
+    let centre = BOARD_SIZE / 2;
+    let mut rng = rand::rng();
+    let mut opening = vec![(centre, centre)];
+    while opening.len() < stones.clamp(1, 25) {
+        let cell = (
+            rng.random_range(centre - 2..=centre + 2),
+            rng.random_range(centre - 2..=centre + 2),
+        );
+        if !opening.contains(&cell) {
+            opening.push(cell);
+        }
+    }
+    opening
+}
+
+#[derive(Default)]
+struct Stats {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+fn elo(score: f64) -> f64 {
+    let score = score.clamp(1e-3, 1.0 - 1e-3);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Stats {
+    fn games(&self) -> f64 {
+        (self.wins + self.draws + self.losses) as f64
+    }
+
+    fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 * 0.5) / self.games()
+    }
+
+    /// Per-game variance of the score.
+    fn variance(&self) -> f64 {
+        let s = self.score();
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / self.games()
+    }
+
+    /// Elo difference and the half-width of its 95% confidence interval.
+    fn elo(&self) -> (f64, f64) {
+        let margin = 1.96 * (self.variance() / self.games()).sqrt();
+        let (score, lo, hi) = (self.score(), self.score() - margin, self.score() + margin);
+        (elo(score), (elo(hi) - elo(lo)) / 2.0)
+    }
+
+    /// Log-likelihood ratio of H1 (`elo1`) against H0 (`elo0`).
+    fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected_score(elo0), expected_score(elo1));
+        (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance) * self.games()
+    }
+}
+
+struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+}
+
+impl Sprt {
+    fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    fn verdict(&self, stats: &Stats) -> Option<&'static str> {
+        let (lower, upper) = self.bounds();
+        let llr = stats.llr(self.elo0, self.elo1);
+        if llr >= upper {
+            Some("H1 accepted")
+        } else if llr <= lower {
+            Some("H0 accepted")
+        } else {
+            None
+        }
+    }
+}
+
+const USAGE: &str = "usage: gobang-rs match [--engine-a <spec>] [--engine-b <spec>] [--games <n>] \
+[--threads <n>] [--openings <file> | --random-stones <n>] [--sprt <elo0>,<elo1>] [--out <file>]
+  engine spec: depth=<n>,movetime=<ms>,profile=<name>,weights=<file>,rule=<freestyle|standard>";
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut a = EngineConfig::parse("")?;
+    let mut b = EngineConfig::parse("")?;
+    let mut games: usize = 20;
+    let mut threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut book: Option<Vec<Vec<(usize, usize)>>> = None;
+    let mut random_stones = 3;
+    let mut sprt = None;
+    let mut out = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(USAGE);
+        match arg.as_str() {
+            "--engine-a" => a = EngineConfig::parse(value()?)?,
+            "--engine-b" => b = EngineConfig::parse(value()?)?,
+            "--games" => games = value()?.parse().map_err(|_| USAGE)?,
+            "--threads" => threads = value()?.parse().map_err(|_| USAGE)?,
+            "--random-stones" => random_stones = value()?.parse().map_err(|_| USAGE)?,
+            "--openings" => {
+                let path = value()?;
+                let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+                let openings = content
+                    .lines()
+                    .filter(|l| !l.trim().is_empty() && !l.starts_with('#'))
+                    .map(|l| notation::parse_moves(l).filter(|m| !m.is_empty()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| format!("{}: malformed opening", path))?;
+                book = Some(openings);
+            }
+            "--sprt" => {
+                let (elo0, elo1) = value()?.split_once(',').ok_or(USAGE)?;
+                sprt = Some(Sprt {
+                    elo0: elo0.parse().map_err(|_| USAGE)?,
+                    elo1: elo1.parse().map_err(|_| USAGE)?,
+                    alpha: 0.05,
+                    beta: 0.05,
+                });
+            }
+            "--out" => out = Some(value()?.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    if games == 0 {
+        return Err(USAGE.to_string());
+    }
+    if book.as_ref().is_some_and(|b| b.is_empty()) {
+        return Err("opening book is empty".to_string());
+    }
+    let exact_five = match (a.exact_five, b.exact_five) {
+        (Some(a), Some(b)) if a != b => {
+            return Err("both engines must play the same rule".to_string());
+        }
+        (a, b) => a.or(b).unwrap_or(false),
+    };
+
+    // Each opening is shared by a pair of games, engine A playing black in the first.
+    let openings: Vec<Vec<(usize, usize)>> = (0..games.div_ceil(2))
+        .map(|i| match &book {
+            Some(book) => book[i % book.len()].clone(),
+            None => random_opening(random_stones),
+        })
+        .collect();
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+    let mut stats = Stats::default();
+    let mut records = Vec::new();
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            let tx = tx.clone();
+            let (a, b, openings, next, stop) = (&a, &b, &openings, &next, &stop);
+            s.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= games {
+                        break;
+                    }
+                    let a_black = i % 2 == 0;
+                    let (black, white) = if a_black { (a, b) } else { (b, a) };
+                    let game = play(black, white, &openings[i / 2], exact_five);
+                    if tx.send((i, a_black, game)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for (i, a_black, game) in rx {
+            let a_score = if a_black {
+                game.result
+            } else {
+                1.0 - game.result
+            };
+            if a_score > 0.5 {
+                stats.wins += 1;
+            } else if a_score < 0.5 {
+                stats.losses += 1;
+            } else {
+                stats.draws += 1;
+            }
+            println!(
+                "game {} ({} moves, A {}): {} - {} - {}",
+                i + 1,
+                game.moves.len(),
+                if a_black { "black" } else { "white" },
+                stats.wins,
+                stats.losses,
+                stats.draws
+            );
+            records.push(game);
+            if sprt
+                .as_ref()
+                .is_some_and(|sprt| sprt.verdict(&stats).is_some())
+            {
+                stop.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+
+    let (diff, margin) = stats.elo();
+    println!(
+        "Score of A vs B: {} - {} - {} [{:.3}] {}",
+        stats.wins,
+        stats.losses,
+        stats.draws,
+        stats.score(),
+        stats.games()
+    );
+    println!("Elo difference: {:.1} +/- {:.1}", diff, margin);
+    if let Some(sprt) = &sprt {
+        let (lower, upper) = sprt.bounds();
+        println!(
+            "SPRT: llr {:.2} ({:.2}, {:.2}) [{}, {}]: {}",
+            stats.llr(sprt.elo0, sprt.elo1),
+            lower,
+            upper,
+            sprt.elo0,
+            sprt.elo1,
+            sprt.verdict(&stats).unwrap_or("inconclusive")
+        );
+    }
+    if let Some(out) = out {
+        let lines: Vec<String> = records.iter().map(CorpusGame::to_line).collect();
+        fs::write(&out, lines.join("\n") + "\n").map_err(|e| e.to_string())?;
+        println!("games written to {}", out);
+    }
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! Offline Texel-style tuning of the evaluation weights.
+//!
+//! The corpus is a text file with one game per line: the result from black's point of
+//! view (`1-0`, `0-1` or `1/2-1/2`) followed by the moves in standard notation, e.g.
+//! `1-0 h8 i9 h9 h10 ...`. Every non-terminal position is scored with the static
+//! evaluation, squashed by a sigmoid into an expected result, and the weights are
+//! adjusted by coordinate descent to minimise the mean squared error to the outcomes.
+//! Piskvork `.psq` game files can be given alongside or instead of corpus files.
+
+use std::{fs, thread};
+
+use crate::{
+    ai::{AI, GameState},
+    notation, psq,
+    weights::Weights,
+};
+
+/// Positions with fewer stones than this are too close to the opening to be informative.
+const MIN_PLY: usize = 4;
+
+pub struct CorpusGame {
+    pub moves: Vec<(usize, usize)>,
+    /// 1.0 if black won, 0.0 if white won, 0.5 for a draw.
+    pub result: f64,
+}
+
+impl CorpusGame {
+    pub fn to_line(&self) -> String {
+        let result = if self.result > 0.5 {
+            "1-0"
+        } else if self.result < 0.5 {
+            "0-1"
+        } else {
+            "1/2-1/2"
+        };
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .map(|&m| notation::to_notation(m))
+            .collect();
+        format!("{} {}", result, moves.join(" "))
+    }
+}
+
+pub fn parse_corpus(content: &str) -> Result<Vec<CorpusGame>, String> {
+    let mut games = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (result, moves) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let result = match result {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            _ => return Err(format!("line {}: unknown result {:?}", i + 1, result)),
+        };
+        let moves = notation::parse_moves(moves)
+            .ok_or_else(|| format!("line {}: malformed move list", i + 1))?;
+        games.push(CorpusGame { moves, result });
+    }
+    Ok(games)
+}
+
+/// Replays `moves` and scores the final position, or `None` if the game is unfinished.
+fn replay_result(moves: &[(usize, usize)]) -> Option<f64> {
+    let mut ai = AI::new();
+    ai.ai_black = true;
+    for (ply, &(x, y)) in moves.iter().enumerate() {
+        if ply % 2 == 0 {
+            ai.ai_step(x, y);
+        } else {
+            ai.human_step(x, y);
+        }
+        if ai.is_game_over() {
+            break;
+        }
+    }
+    match ai.state {
+        GameState::AI => Some(1.0),
+        GameState::Human => Some(0.0),
+        GameState::Draw => Some(0.5),
+        GameState::Idle => None,
+    }
+}
+
+/// Reads a corpus file, or a Piskvork `.psq` game scored by replaying it. Unfinished
+/// `.psq` games, e.g. lost on time, carry no result and are skipped.
+fn load_corpus(path: &str) -> Result<Vec<CorpusGame>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    if !path.to_ascii_lowercase().ends_with(".psq") {
+        return parse_corpus(&content).map_err(|e| format!("{}: {}", path, e));
+    }
+    let moves: Vec<_> = psq::parse(&content)
+        .map_err(|e| format!("{}: {}", path, e))?
+        .into_iter()
+        .map(|(step, _)| step)
+        .collect();
+    Ok(replay_result(&moves)
+        .map(|result| CorpusGame { moves, result })
+        .into_iter()
+        .collect())
+}
+
+fn sigmoid(k: f64, eval: f64) -> f64 {
+    1.0 / (1.0 + (-k * eval).exp())
+}
+
+/// Replays `game` and returns the squared errors summed over its positions and the
+/// number of positions.
+fn game_error(game: &CorpusGame, weights: &Weights, k: f64) -> (f64, usize) {
+    let mut ai = AI::new();
+    ai.ai_black = true;
+    ai.set_weights(weights.clone());
+    let (mut error, mut count) = (0.0, 0);
+    for (ply, &(x, y)) in game.moves.iter().enumerate() {
+        // Black stones are played as the AI's, so `is_ai` means black to move.
+        let black_to_move = ply % 2 == 0;
+        if ply >= MIN_PLY {
+            let eval = ai.evaluate(black_to_move) as f64;
+            let result = if black_to_move {
+                game.result
+            } else {
+                1.0 - game.result
+            };
+            error += (result - sigmoid(k, eval)).powi(2);
+            count += 1;
+        }
+        if black_to_move {
+            ai.ai_step(x, y);
+        } else {
+            ai.human_step(x, y);
+        }
+        if ai.is_game_over() {
+            break;
+        }
+    }
+    (error, count)
+}
+
+/// Mean squared error of the corpus, spread over all available cores.
+fn corpus_error(games: &[CorpusGame], weights: &Weights, k: f64) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk = games.len().div_ceil(threads).max(1);
+    let (error, count) = thread::scope(|s| {
+        games
+            .chunks(chunk)
+            .map(|games| {
+                s.spawn(move || {
+                    games
+                        .iter()
+                        .map(|game| game_error(game, weights, k))
+                        .fold((0.0, 0), |(e, c), (ge, gc)| (e + ge, c + gc))
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("Tuning thread panicked"))
+            .fold((0.0, 0), |(e, c), (ge, gc)| (e + ge, c + gc))
+    });
+    if count == 0 {
+        0.0
+    } else {
+        error / count as f64
+    }
+}
+
+/// Finds the sigmoid scale that best fits the corpus with the starting weights.
+fn fit_k(games: &[CorpusGame], weights: &Weights) -> f64 {
+    let (mut lo, mut hi) = (-7.0f64, -1.0f64);
+    for _ in 0..20 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if corpus_error(games, weights, 10f64.powf(m1))
+            < corpus_error(games, weights, 10f64.powf(m2))
+        {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+    10f64.powf((lo + hi) / 2.0)
+}
+
+/// Applies a signed step to the `i`-th tunable parameter.
+fn nudge(weights: &Weights, i: usize, sign: i32) -> Weights {
+    let mut weights = weights.clone();
+    let tunable: Vec<usize> = tunable_shapes(&weights);
+    if let Some(&shape) = tunable.get(i) {
+        let score = &mut weights.shapes[shape].score;
+        *score = (*score + sign * (*score / 10).max(1)).max(1);
+    } else if i == tunable.len() {
+        weights.defence = (weights.defence + sign as f32 * 0.02).max(0.0);
+    } else {
+        weights.combination_bonus = (weights.combination_bonus + sign as f32 * 0.1).max(0.0);
+    }
+    weights
+}
+
+/// Every shape except five in a row, whose score marks a win rather than a guess.
+fn tunable_shapes(weights: &Weights) -> Vec<usize> {
+    (0..weights.shapes.len())
+        .filter(|&i| weights.shapes[i].pattern != [1, 1, 1, 1, 1])
+        .collect()
+}
+
+pub fn tune(games: &[CorpusGame], mut weights: Weights, iterations: usize) -> Weights {
+    let k = fit_k(games, &weights);
+    let mut best = corpus_error(games, &weights, k);
+    println!("k = {:.3e}, initial error = {:.6}", k, best);
+    let params = tunable_shapes(&weights).len() + 2;
+    for iteration in 1..=iterations {
+        let mut improved = false;
+        for i in 0..params {
+            for sign in [1, -1] {
+                let candidate = nudge(&weights, i, sign);
+                let error = corpus_error(games, &candidate, k);
+                if error < best {
+                    best = error;
+                    weights = candidate;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+        println!("iteration {}: error = {:.6}", iteration, best);
+        if !improved {
+            break;
+        }
+    }
+    weights
+}
+
+const USAGE: &str = "usage: gobang-rs tune <corpus|game.psq>... [--weights <file>] \
+[--iterations <n>] [--out <file>]";
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut corpus = Vec::new();
+    let mut weights = Weights::default();
+    let mut iterations = 10;
+    let mut out = String::from("weights.json");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--weights" => {
+                let path = args.next().ok_or(USAGE)?;
+                let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+                weights = Weights::parse(path, &content)?;
+            }
+            "--iterations" => {
+                iterations = args.next().and_then(|n| n.parse().ok()).ok_or(USAGE)?;
+            }
+            "--out" => out = args.next().ok_or(USAGE)?.clone(),
+            path if !path.starts_with("--") => corpus.push(path.to_string()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    if corpus.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let mut games = Vec::new();
+    for path in &corpus {
+        games.extend(load_corpus(path)?);
+    }
+    println!("{} games loaded from {} files", games.len(), corpus.len());
+    let weights = tune(&games, weights, iterations);
+    fs::write(&out, weights.dump(&out)).map_err(|e| e.to_string())?;
+    println!("weights written to {}", out);
+    Ok(())
+}
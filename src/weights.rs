@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub score: i32,
+    pub pattern: Vec<usize>,
+}
+
+/// Evaluation parameters used by `AI::evalution` and `AI::cal_score`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weights {
+    pub shapes: Vec<Shape>,
+    /// Multiplier applied to the side to move's own shape score.
+    pub attack: f32,
+    /// Multiplier applied to the opponent's shape score before subtracting it.
+    pub defence: f32,
+    /// Two crossing shapes both scoring above this are treated as a combination.
+    pub combination_threshold: i32,
+    /// Multiplier applied to the summed scores of a combination.
+    pub combination_bonus: f32,
+}
+
+const SHAPE_SCORE: &[(i32, &[usize])] = &[
+    (50, &[0, 1, 1, 0, 0]),
+    (50, &[0, 0, 1, 1, 0]),
+    (200, &[1, 1, 0, 1, 0]),
+    (500, &[0, 0, 1, 1, 1]),
+    (500, &[1, 1, 1, 0, 0]),
+    (5000, &[0, 1, 1, 1, 0]),
+    (5000, &[0, 1, 0, 1, 1, 0]),
+    (5000, &[0, 1, 1, 0, 1, 0]),
+    (5000, &[1, 1, 1, 0, 1]),
+    (5000, &[1, 1, 0, 1, 1]),
+    (5000, &[1, 0, 1, 1, 1]),
+    (5000, &[1, 1, 1, 1, 0]),
+    (5000, &[0, 1, 1, 1, 1]),
+    (50000, &[0, 1, 1, 1, 1, 0]),
+    (99999999, &[1, 1, 1, 1, 1]),
+];
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            shapes: SHAPE_SCORE
+                .iter()
+                .map(|(score, pattern)| Shape {
+                    score: *score,
+                    pattern: pattern.to_vec(),
+                })
+                .collect(),
+            attack: 1.0,
+            defence: 0.1,
+            combination_threshold: 10,
+            combination_bonus: 1.0,
+        }
+    }
+}
+
+impl Weights {
+    /// Parses a weight file, choosing TOML or JSON by the file name's extension.
+    pub fn parse(file_name: &str, content: &str) -> Result<Self, String> {
+        if file_name.ends_with(".toml") {
+            toml::from_str(content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(content).map_err(|e| e.to_string())
+        }
+    }
+
+    /// Serializes the weights in the format matching the file name's extension.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn dump(&self, file_name: &str) -> String {
+        if file_name.ends_with(".toml") {
+            toml::to_string_pretty(self).expect("Can not serialize weights")
+        } else {
+            serde_json::to_string_pretty(self).expect("Can not serialize weights")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Profile {
+    Balanced,
+    Aggressive,
+    Defensive,
+    Custom,
+}
+
+impl Profile {
+    pub const BUILTIN: [Profile; 3] = [Profile::Balanced, Profile::Aggressive, Profile::Defensive];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Balanced => "Balanced",
+            Profile::Aggressive => "Aggressive",
+            Profile::Defensive => "Defensive",
+            Profile::Custom => "Custom",
+        }
+    }
+
+    /// Weights of a built-in profile; `Custom` falls back to the balanced set.
+    pub fn weights(&self) -> Weights {
+        match self {
+            Profile::Aggressive => Weights {
+                defence: 0.05,
+                combination_bonus: 1.5,
+                ..Weights::default()
+            },
+            Profile::Defensive => Weights {
+                attack: 0.8,
+                defence: 0.6,
+                ..Weights::default()
+            },
+            Profile::Balanced | Profile::Custom => Weights::default(),
+        }
+    }
+}